@@ -1,56 +1,284 @@
 use clap::{App, AppSettings, Arg, };
 use zmq::{Context, Socket};
-use crate::socket::{SocketParameters, create_socket};
+use crate::encoding::Encoding;
+use crate::monitor;
+use crate::options::SocketOptions;
+use crate::socket::{AssociationType, SocketParameters, SocketType, StreamEvent, StreamPeers, Subscription, associate, configure_socket, format_peer_id, parse_peer_id, unbind};
+use crate::validation;
 use std::error::Error;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread::{self, JoinHandle};
+
+/// Wakes the chat thread up when a line has been typed in
+const INPUT_ENDPOINT: &str = "inproc://rzmq.chat.input";
+
+const HELP: &str = "\
+<message>                   send the message, or a line starting with / using --send <message>
+--id <id> --send <message>  send to the peer with the given routing id
+/multipart <frame>...       send the frames as one message, quote frames holding spaces
+/sub [topic]                subscribe, to everything without a topic
+/unsub [topic]              unsubscribe
+/connect <endpoint>         connect to one more endpoint
+/disconnect <endpoint>      disconnect from the endpoint
+/bind <endpoint>            bind one more endpoint
+/unbind <endpoint>          unbind the endpoint
+/set <sockopt> <value>      set a socket option to a number or true/false, e.g. /set reconnect-ivl 500
+/hex                        switch typed in and received frames between hex and the initial encoding
+/peers                      list the peers of a STREAM socket
+/peer <id>                  select the STREAM peer to send to
+/info                       show the socket type, endpoints and identity
+/help                       show this help
+/quit                       leave the chat";
 
 pub struct Chat {
-    #[allow(dead_code)]
     ctx: Context,
-    socket: Socket
+    socket: Socket,
+    socket_type: SocketType,
+    endpoints: Vec<(AssociationType, String)>,
+    stream: Option<StreamPeers>,
+    peer: Option<Vec<u8>>,
+    encoding: Encoding,
+    /// Encoding to go back to when `/hex` is toggled off
+    hex_toggled_from: Option<Encoding>,
 }
 
 impl Chat {
     pub fn new(parameters: &SocketParameters) -> Result<Self, Box<dyn Error>> {
         let ctx = zmq::Context::new();
-        let socket = create_socket(&ctx, &parameters)?;
-        socket.set_rcvtimeo(100)?;
+        let socket = configure_socket(&ctx, parameters)?;
+        if parameters.monitor {
+            monitor::watch(&ctx, &socket)?;
+        }
+        // Kept as bound, so that /info shows and /unbind takes the port picked for a wildcard
+        let endpoints = associate(&socket, parameters)?;
+        if parameters.options.rcvtimeo.is_none() {
+            socket.set_rcvtimeo(100)?;
+        }
+
+        let stream = match parameters.socket_type {
+            SocketType::STREAM => Some(StreamPeers::default()),
+            _ => None,
+        };
+
+        Ok(Self {
+            ctx,
+            socket,
+            socket_type: parameters.socket_type,
+            endpoints,
+            stream,
+            peer: None,
+            encoding: Encoding::default(),
+            hex_toggled_from: None,
+        })
+    }
 
-        Ok(Self { ctx, socket })
+    /// Sets the encoding of typed in messages and received frames
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
     }
 
     pub fn send(&self, message: &str) -> Result<(), Box<dyn Error>> {
+        let message = self.encoding.decode(message)?;
+
+        if self.stream.is_some() {
+            let peer = self.peer.as_ref().ok_or("No peer selected, use :peer <id>")?;
+            self.socket.send_multipart([peer.as_slice(), &message], 0)?;
+            return Ok(());
+        }
+
         self.socket.send(message, 0)?;
         Ok(())
     }
 
+    pub fn send_multipart(&self, frames: &[String]) -> Result<(), Box<dyn Error>> {
+        if self.stream.is_some() {
+            return Err("STREAM sockets send raw bytes, not multipart messages".into());
+        }
+        let frames = frames.iter().map(|frame| self.encoding.decode(frame)).collect::<Result<Vec<_>, _>>()?;
+        self.socket.send_multipart(frames, 0)?;
+        Ok(())
+    }
+
     pub fn send_with_id(&self, id: &str, message: &str) -> Result<(), Box<dyn Error>> {
-        self.socket.send_multipart(&[id, message], 0)?;
+        let message = self.encoding.decode(message)?;
+
+        if self.stream.is_some() {
+            self.socket.send_multipart([parse_peer_id(id)?.as_slice(), &message], 0)?;
+            return Ok(());
+        }
+
+        self.socket.send_multipart([id.as_bytes(), &message], 0)?;
         Ok(())
     }
 
+    pub fn is_stream(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Peers connected to a STREAM socket, the selected one first
+    pub fn peers(&self) -> Vec<String> {
+        let mut peers = self.stream
+            .iter()
+            .flat_map(StreamPeers::iter)
+            .filter(|id| Some(*id) != self.peer.as_ref())
+            .map(|id| format_peer_id(id))
+            .collect::<Vec<_>>();
+        if let Some(peer) = &self.peer {
+            peers.insert(0, format_peer_id(peer));
+        }
+        peers
+    }
+
+    pub fn select_peer(&mut self, id: &str) -> Result<(), Box<dyn Error>> {
+        let id = parse_peer_id(id)?;
+        match &self.stream {
+            Some(peers) if peers.contains(&id) => {
+                self.peer = Some(id);
+                Ok(())
+            }
+            Some(_) => Err("Unknown peer".into()),
+            None => Err("Peers are only supported by STREAM sockets".into()),
+        }
+    }
+
+    /// Receives a connection, disconnection or data event from a STREAM socket.
+    /// The first peer to connect is selected automatically.
+    pub fn receive_stream(&mut self) -> Result<String, Box<dyn Error>> {
+        let frames = self.socket.recv_multipart(0)?;
+        let peers = self.stream.as_mut().ok_or("Not a STREAM socket")?;
+        let event = peers.event(&frames).ok_or("Unexpected STREAM message")?;
+
+        match event {
+            StreamEvent::Connected(id) if self.peer.is_none() => self.peer = Some(id.to_vec()),
+            StreamEvent::Disconnected(id) if self.peer.as_deref() == Some(id) => self.peer = None,
+            _ => {}
+        }
+
+        Ok(event.describe(self.encoding))
+    }
+
+    pub fn subscribe(&self, topic: &str) -> Result<(), Box<dyn Error>> {
+        self.update_subscription(Subscription::Subscribe(topic.as_bytes()))
+    }
+
+    pub fn unsubscribe(&self, topic: &str) -> Result<(), Box<dyn Error>> {
+        self.update_subscription(Subscription::Unsubscribe(topic.as_bytes()))
+    }
+
+    fn update_subscription(&self, subscription: Subscription) -> Result<(), Box<dyn Error>> {
+        match (self.socket.get_socket_type()?, subscription) {
+            (zmq::SUB, Subscription::Subscribe(topic)) => self.socket.set_subscribe(topic)?,
+            (zmq::SUB, Subscription::Unsubscribe(topic)) => self.socket.set_unsubscribe(topic)?,
+            (zmq::XSUB, subscription) => self.socket.send(subscription.encode(), 0)?,
+            _ => return Err("Subscriptions are only supported by SUB and XSUB sockets".into()),
+        }
+        Ok(())
+    }
+
+    /// Binds or connects to one more endpoint, returns the endpoint as the socket reports it,
+    /// e.g. with the port picked for a wildcard bind
+    pub fn add_endpoint(&mut self, association_type: AssociationType, endpoint: &str) -> Result<String, Box<dyn Error>> {
+        validation::validate_socket(endpoint.to_string())?;
+        let endpoint = match association_type {
+            AssociationType::Connect => {
+                self.socket.connect(endpoint)?;
+                endpoint.to_string()
+            }
+            AssociationType::Bind => {
+                self.socket.bind(endpoint)?;
+                self.socket.get_last_endpoint()?.map_err(|_| "Bound endpoint is not valid UTF-8")?
+            }
+        };
+        self.endpoints.push((association_type, endpoint.clone()));
+        Ok(endpoint)
+    }
+
+    /// Unbinds or disconnects from an endpoint, which has to be given as reported when it was added
+    pub fn remove_endpoint(&mut self, association_type: AssociationType, endpoint: &str) -> Result<(), Box<dyn Error>> {
+        match association_type {
+            AssociationType::Connect => self.socket.disconnect(endpoint)?,
+            AssociationType::Bind => unbind(&mut self.socket, endpoint)?,
+        }
+        self.endpoints.retain(|(added_as, added)| *added_as != association_type || added != endpoint);
+        Ok(())
+    }
+
+    /// Sets a socket option by name, e.g. `sndhwm` to `100`
+    pub fn set_option(&self, name: &str, value: &str) -> Result<(), Box<dyn Error>> {
+        let options = SocketOptions::parse(name, value)?;
+        options.validate(&self.socket_type)?;
+        options.apply(&self.socket)
+    }
+
+    /// Switches to hex and back to the previous encoding, returns the encoding now used
+    pub fn toggle_hex(&mut self) -> Encoding {
+        self.encoding = match self.hex_toggled_from.take() {
+            Some(encoding) => encoding,
+            None => {
+                self.hex_toggled_from = Some(self.encoding);
+                Encoding::Hex
+            }
+        };
+        self.encoding
+    }
+
+    /// Socket type, endpoints and identity, one per line
+    pub fn info(&self) -> Result<String, Box<dyn Error>> {
+        let endpoints = self.endpoints
+            .iter()
+            .map(|(association_type, endpoint)| match association_type {
+                AssociationType::Bind => format!("bind {}", endpoint),
+                AssociationType::Connect => format!("connect {}", endpoint),
+            })
+            .collect::<Vec<_>>();
+        let identity = self.socket.get_identity()?;
+
+        Ok(format!("type: {}\nendpoints: {}\nidentity: {}",
+                   self.socket_type,
+                   if endpoints.is_empty() { "none".to_string() } else { endpoints.join(", ") },
+                   if identity.is_empty() { "none".to_string() } else { Encoding::Auto.display(&identity) }))
+    }
+
     pub fn receive(&self) -> Result<Vec<String>, Box<dyn Error>> {
         let message = self.socket.recv_multipart(0)?;
         let result = message
             .iter()
-            .map(|part | {
-                String::from_utf8(part.to_vec())
-            }).collect::<Result<Vec<_>, _>>()?;
+            .map(|part | self.encoding.encode(part))
+            .collect::<Vec<_>>();
         Ok(result)
 
     }
 }
-pub fn chat(parameters: SocketParameters) -> Result<(), Box<dyn Error>> {
-    println!("Chat {:?}", parameters.address);
+pub fn chat(parameters: SocketParameters, encoding: Encoding) -> Result<(), Box<dyn Error>> {
+    eprintln!("Chat {:?}", parameters.addresses().join(", "));
 
     let mut chat = Chat::new(&parameters)?;
-    let mut rl = rustyline::Editor::<()>::new();
+    chat.set_encoding(encoding);
+    let (input, wakeup) = input_sockets(&chat.ctx)?;
+
+    let mut rl = rustyline::Editor::<()>::new()?;
     let _ = rl.load_history("history.txt");
+    // Printing through rustyline keeps the prompt and what has been typed so far below the printed lines
+    let print: Box<dyn FnMut(String) + Send> = match rl.create_external_printer() {
+        Ok(mut printer) => Box::new(move |line| {
+            let _ = rustyline::ExternalPrinter::print(&mut printer, format!("{}\n", line));
+        }),
+        Err(_) => Box::new(|line| println!("{}", line)),
+    };
+
+    let (commands, received_commands) = mpsc::channel();
+    let background = run_in_background(chat, wakeup, received_commands, print);
     loop {
         let readline = rl.readline(">> ");
         match readline {
             Ok(line) => {
                 rl.add_history_entry(line.as_str());
-                execute_chat_command(&mut chat, parse_chat_command(line));
+                let command = parse_chat_command(line);
+                if command == ChatCommand::Quit || commands.send(command).is_err() {
+                    break;
+                }
+                // Not blocking in case the chat thread is gone, a wake up still pending is just as good
+                let _ = input.send("", zmq::DONTWAIT);
             },
             Err(rustyline::error::ReadlineError::Interrupted) | Err(rustyline::error::ReadlineError::Eof) => {
                 break
@@ -61,27 +289,92 @@ pub fn chat(parameters: SocketParameters) -> Result<(), Box<dyn Error>> {
             }
         }
     }
+
+    // The chat thread stops once it finds no one left to send it commands
+    drop(commands);
+    let _ = input.send("", zmq::DONTWAIT);
+    let _ = background.join();
     rl.save_history("history.txt")?;
     Ok(())
 }
 
+/// Pair of sockets for the typing thread to wake up the chat thread
+fn input_sockets(ctx: &Context) -> Result<(Socket, Socket), Box<dyn Error>> {
+    let input = ctx.socket(zmq::PAIR)?;
+    input.set_linger(0)?;
+    input.bind(INPUT_ENDPOINT)?;
+    let wakeup = ctx.socket(zmq::PAIR)?;
+    wakeup.connect(INPUT_ENDPOINT)?;
+    Ok((input, wakeup))
+}
+
+/// Moves the chat to a thread of its own, which prints messages as they arrive and executes the typed in commands
+fn run_in_background(mut chat: Chat, wakeup: Socket, commands: Receiver<ChatCommand>,
+                     mut print: Box<dyn FnMut(String) + Send>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        loop {
+            let (received, typed) = {
+                let mut items = [chat.socket.as_poll_item(zmq::POLLIN), wakeup.as_poll_item(zmq::POLLIN)];
+                if let Err(err) = zmq::poll(&mut items, -1) {
+                    return print(format!("error: {}", err));
+                }
+                (items[0].is_readable(), items[1].is_readable())
+            };
+
+            if received {
+                if let Some(message) = receive_next(&mut chat) {
+                    print(message);
+                }
+            }
+            if typed {
+                let _ = wakeup.recv_bytes(0);
+                loop {
+                    match commands.try_recv() {
+                        Ok(command) => if let Some(output) = execute_chat_command(&mut chat, command) {
+                            print(output);
+                        },
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => return,
+                    }
+                }
+            }
+        }
+    })
+}
+
 #[derive(Debug, PartialEq)]
 enum ChatCommand {
-    Receive,
+    /// Blank line, received messages are printed as they arrive without asking
+    Empty,
     Send(String),
-    SendTo(String, String)
+    SendTo(String, String),
+    Subscribe(String),
+    Unsubscribe(String),
+    ListPeers,
+    SelectPeer(String),
+    SendMultipart(Vec<String>),
+    Connect(String),
+    Disconnect(String),
+    Bind(String),
+    Unbind(String),
+    Set(String, String),
+    ToggleHex,
+    Info,
+    Help,
+    Quit,
+    /// Mistyped command, with what is wrong
+    Invalid(String),
 }
 
 
 fn tokenize(input: &str) -> Vec<String> {
     let mut r = Vec::<String>::new();
 
-    for c in regex::Regex::new("['\"](.+)['\"]|([^\\s\"']\\S*[^\\s\"'])")
+    // Quoted tokens end at the same quote they start with and may be empty
+    for c in regex::Regex::new(r#"'([^']*)'|"([^"]*)"|(\S+)"#)
         .unwrap()
         .captures_iter(input) {
-        if let Some(m)  = c.get(1) {
-            r.push(m.as_str().to_string());
-        } else if let Some(m)  = c.get(2) {
+        if let Some(m) = c.get(1).or_else(|| c.get(2)).or_else(|| c.get(3)) {
             r.push(m.as_str().to_string());
         }
     }
@@ -89,19 +382,63 @@ fn tokenize(input: &str) -> Vec<String> {
     r
 }
 
+fn parse_slash_command(input: &str) -> ChatCommand {
+    let (name, argument) = match input.find(char::is_whitespace) {
+        Some(index) => (&input[..index], input[index..].trim()),
+        None => (input, ""),
+    };
+    let required = |command: fn(String) -> ChatCommand| match argument {
+        "" => ChatCommand::Invalid(format!("/{} needs an argument, see /help", name)),
+        argument => command(argument.to_string()),
+    };
+
+    match name {
+        "sub" => ChatCommand::Subscribe(argument.to_string()),
+        "unsub" => ChatCommand::Unsubscribe(argument.to_string()),
+        "connect" => required(ChatCommand::Connect),
+        "disconnect" => required(ChatCommand::Disconnect),
+        "bind" => required(ChatCommand::Bind),
+        "unbind" => required(ChatCommand::Unbind),
+        "peer" => required(ChatCommand::SelectPeer),
+        "peers" => ChatCommand::ListPeers,
+        "set" => match tokenize(argument).as_slice() {
+            [option, value] => ChatCommand::Set(option.clone(), value.clone()),
+            _ => ChatCommand::Invalid("Usage: /set <sockopt> <value>".to_string()),
+        },
+        "multipart" => match tokenize(argument) {
+            frames if frames.is_empty() => ChatCommand::Invalid("Usage: /multipart <frame>...".to_string()),
+            frames => ChatCommand::SendMultipart(frames),
+        },
+        "hex" => ChatCommand::ToggleHex,
+        "info" => ChatCommand::Info,
+        "help" => ChatCommand::Help,
+        "quit" => ChatCommand::Quit,
+        _ => ChatCommand::Invalid(format!("Unknown command /{}, see /help", name)),
+    }
+}
+
 fn parse_chat_command(input: String) -> ChatCommand {
+    if let Some(command) = input.strip_prefix('/') {
+        return parse_slash_command(command);
+    } else if let Some(topic) = input.strip_prefix(":subscribe") {
+        return ChatCommand::Subscribe(topic.trim().to_string());
+    } else if let Some(topic) = input.strip_prefix(":unsubscribe") {
+        return ChatCommand::Unsubscribe(topic.trim().to_string());
+    } else if input.trim() == ":peers" {
+        return ChatCommand::ListPeers;
+    } else if let Some(id) = input.strip_prefix(":peer ") {
+        return ChatCommand::SelectPeer(id.trim().to_string());
+    }
+
     let matches = App::new("chat")
         .setting(AppSettings::NoBinaryName)
         .setting(AppSettings::InferSubcommands)
-        .arg(Arg::with_name("receive").long("receive").short("r").takes_value(false))
-        .arg(Arg::with_name("send").long("send").short("s").takes_value(true).conflicts_with("receive"))
-        .arg(Arg::with_name("receiver id").long("id").takes_value(true).conflicts_with("receive"))
-        .get_matches_from_safe(tokenize(input.as_str()).into_iter());
+        .arg(Arg::with_name("send").long("send").short("s").takes_value(true))
+        .arg(Arg::with_name("receiver id").long("id").takes_value(true))
+        .get_matches_from_safe(tokenize(input.as_str()));
 
     if let Ok(m) = matches {
-        if m.is_present("receive") {
-            return ChatCommand::Receive;
-        } else if m.is_present("send") {
+        if m.is_present("send") {
             if m.is_present("receiver id") {
                 return ChatCommand::SendTo(m.value_of("receiver id").unwrap().to_string(),
                                            m.values_of("send").unwrap().map(str::to_string).collect());
@@ -110,35 +447,52 @@ fn parse_chat_command(input: String) -> ChatCommand {
         }
     }
 
-    if input.is_empty() {
-        return ChatCommand::Receive;
+    if input.trim().is_empty() {
+        return ChatCommand::Empty;
     }
 
     ChatCommand::Send(input)
 }
 
-fn execute_chat_command(chat: &mut Chat, command: ChatCommand) {
-    match command {
-        ChatCommand::Receive => {
-            if let Ok(message) = chat.receive() {
-                println!("received: {:?}", message);
-            }
-        },
-        ChatCommand::Send(message) => {
-            match chat.send(&message) {
-                Ok(_) => println!("sent: {}", message),
-                Err(err) => println!("error: {}", err)
-            }
-        },
-        ChatCommand::SendTo(id, message) => {
-            match chat.send_with_id(&id, &message) {
-                Ok(_) => println!("sent: {}", message),
-                Err(err) => println!("error: {}", err)
-            }
-        },
+/// Receives a message or a STREAM event, described for printing
+fn receive_next(chat: &mut Chat) -> Option<String> {
+    if chat.is_stream() {
+        chat.receive_stream().ok()
+    } else {
+        chat.receive().ok().map(|message| format!("received: {:?}", message))
     }
 }
 
+/// Executes the command, returning what to print
+fn execute_chat_command(chat: &mut Chat, command: ChatCommand) -> Option<String> {
+    let result = match command {
+        ChatCommand::Empty => return None,
+        ChatCommand::Send(message) => chat.send(&message).map(|_| format!("sent: {}", message)),
+        ChatCommand::SendTo(id, message) => chat.send_with_id(&id, &message).map(|_| format!("sent: {}", message)),
+        ChatCommand::Subscribe(topic) => chat.subscribe(&topic).map(|_| format!("subscribed: {:?}", topic)),
+        ChatCommand::Unsubscribe(topic) => chat.unsubscribe(&topic).map(|_| format!("unsubscribed: {:?}", topic)),
+        ChatCommand::ListPeers => Ok(format!("peers: {:?}", chat.peers())),
+        ChatCommand::SelectPeer(id) => chat.select_peer(&id).map(|_| format!("selected peer: {}", id)),
+        ChatCommand::SendMultipart(frames) => chat.send_multipart(&frames).map(|_| format!("sent: {:?}", frames)),
+        ChatCommand::Connect(endpoint) => chat.add_endpoint(AssociationType::Connect, &endpoint)
+            .map(|endpoint| format!("connected: {}", endpoint)),
+        ChatCommand::Disconnect(endpoint) => chat.remove_endpoint(AssociationType::Connect, &endpoint)
+            .map(|_| format!("disconnected: {}", endpoint)),
+        ChatCommand::Bind(endpoint) => chat.add_endpoint(AssociationType::Bind, &endpoint)
+            .map(|endpoint| format!("bound: {}", endpoint)),
+        ChatCommand::Unbind(endpoint) => chat.remove_endpoint(AssociationType::Bind, &endpoint)
+            .map(|_| format!("unbound: {}", endpoint)),
+        ChatCommand::Set(option, value) => chat.set_option(&option, &value).map(|_| format!("set: {} = {}", option, value)),
+        ChatCommand::ToggleHex => Ok(format!("encoding: {:?}", chat.toggle_hex())),
+        ChatCommand::Info => chat.info(),
+        ChatCommand::Help => Ok(HELP.to_string()),
+        // Normally stopped by the input loop before reaching the chat thread
+        ChatCommand::Quit => return None,
+        ChatCommand::Invalid(error) => Err(error.into()),
+    };
+    Some(result.unwrap_or_else(|err| format!("error: {}", err)))
+}
+
 
 
 #[cfg(test)]
@@ -151,13 +505,17 @@ mod test {
         assert_eq!(vec!["two words"], tokenize("\"two words\""));
         assert_eq!(vec!["word", "two words"], tokenize("word \"two words\""));
         assert_eq!(vec!["word", "two words"], tokenize("word 'two words'"));
+        assert_eq!(vec!["a", "b"], tokenize("a b"));
+        assert_eq!(vec!["a", "two words", "x y"], tokenize("a 'two words' 'x y'"));
+        assert_eq!(vec!["a", "b"], tokenize("'a' 'b'"));
+        assert_eq!(vec!["it's", "x"], tokenize("\"it's\" x"));
+        assert_eq!(vec!["hdr", "", "body"], tokenize("hdr '' body"));
     }
 
     #[test]
     fn chat_command_parsing() {
-        assert_eq!(ChatCommand::Receive, parse_chat_command("".to_string()));
-        assert_eq!(ChatCommand::Receive, parse_chat_command("--receive".to_string()));
-        assert_eq!(ChatCommand::Receive, parse_chat_command("-r".to_string()));
+        assert_eq!(ChatCommand::Empty, parse_chat_command("".to_string()));
+        assert_eq!(ChatCommand::Empty, parse_chat_command("  ".to_string()));
         assert_eq!(ChatCommand::Send(String::from("message")), parse_chat_command("--send message".to_string()));
         assert_eq!(ChatCommand::Send(String::from("message")), parse_chat_command("-s message".to_string()));
         assert_eq!(ChatCommand::Send(String::from("message")), parse_chat_command("-s message".to_string()));
@@ -166,8 +524,101 @@ mod test {
         assert_eq!(ChatCommand::Send(String::from("multiple words")), parse_chat_command("-s 'multiple words'".to_string()));
         assert_eq!(ChatCommand::SendTo(String::from("ID1"), String::from("message")), parse_chat_command("--id ID1 -s message".to_string()));
         assert_eq!(ChatCommand::Send(String::from("Hi again")), parse_chat_command("--send \"Hi again\"".to_string()));
+        assert_eq!(ChatCommand::Subscribe(String::from("foo")), parse_chat_command(":subscribe foo".to_string()));
+        assert_eq!(ChatCommand::Subscribe(String::from("")), parse_chat_command(":subscribe".to_string()));
+        assert_eq!(ChatCommand::Unsubscribe(String::from("foo")), parse_chat_command(":unsubscribe foo".to_string()));
+        assert_eq!(ChatCommand::ListPeers, parse_chat_command(":peers".to_string()));
+        assert_eq!(ChatCommand::SelectPeer(String::from("0080000001")), parse_chat_command(":peer 0080000001".to_string()));
     }
-}
 
+    #[test]
+    fn slash_command_parsing() {
+        let parse = |input: &str| parse_chat_command(input.to_string());
+
+        assert_eq!(ChatCommand::Subscribe(String::from("foo")), parse("/sub foo"));
+        assert_eq!(ChatCommand::Subscribe(String::from("")), parse("/sub"));
+        assert_eq!(ChatCommand::Unsubscribe(String::from("foo")), parse("/unsub  foo"));
+        assert_eq!(ChatCommand::Connect(String::from("tcp://127.0.0.1:5559")), parse("/connect tcp://127.0.0.1:5559"));
+        assert_eq!(ChatCommand::Disconnect(String::from("tcp://127.0.0.1:5559")), parse("/disconnect tcp://127.0.0.1:5559"));
+        assert_eq!(ChatCommand::Bind(String::from("tcp://*:*")), parse("/bind tcp://*:*"));
+        assert_eq!(ChatCommand::Unbind(String::from("tcp://0.0.0.0:5559")), parse("/unbind tcp://0.0.0.0:5559"));
+        assert_eq!(ChatCommand::Set(String::from("sndhwm"), String::from("10")), parse("/set sndhwm 10"));
+        assert_eq!(ChatCommand::SendMultipart(vec![String::from("a"), String::from("two words")]), parse("/multipart a 'two words'"));
+        assert_eq!(ChatCommand::ToggleHex, parse("/hex"));
+        assert_eq!(ChatCommand::Info, parse("/info"));
+        assert_eq!(ChatCommand::Help, parse("/help"));
+        assert_eq!(ChatCommand::Quit, parse("/quit"));
+        assert_eq!(ChatCommand::ListPeers, parse("/peers"));
+        assert_eq!(ChatCommand::Send(String::from("/not a command")), parse("--send '/not a command'"));
+
+        assert_eq!(ChatCommand::Invalid(String::from("/connect needs an argument, see /help")), parse("/connect"));
+        assert_eq!(ChatCommand::Invalid(String::from("Usage: /set <sockopt> <value>")), parse("/set sndhwm"));
+        assert_eq!(ChatCommand::Invalid(String::from("Usage: /multipart <frame>...")), parse("/multipart"));
+        assert_eq!(ChatCommand::Invalid(String::from("Unknown command /frobnicate, see /help")), parse("/frobnicate"));
+    }
+
+    #[test]
+    fn printing_in_background() {
+        let chat = Chat::new(&SocketParameters {
+            address: "inproc://rzmq.chat.test".into(),
+            socket_type: SocketType::PAIR,
+            association_type: AssociationType::Bind,
+            ..Default::default()
+        }).unwrap();
+        let peer = chat.ctx.socket(zmq::PAIR).unwrap();
+        peer.connect("inproc://rzmq.chat.test").unwrap();
+        let (input, wakeup) = input_sockets(&chat.ctx).unwrap();
+
+        let (printed, printed_lines) = mpsc::channel();
+        let (commands, received_commands) = mpsc::channel();
+        let background = run_in_background(chat, wakeup, received_commands, Box::new(move |line| {
+            let _ = printed.send(line);
+        }));
+        let next_line = || printed_lines.recv_timeout(std::time::Duration::from_secs(1)).unwrap();
+
+        commands.send(parse_chat_command("first".to_string())).unwrap();
+        input.send("", 0).unwrap();
+        assert_eq!("sent: first", next_line());
+        assert_eq!(b"first".to_vec(), peer.recv_bytes(0).unwrap());
+
+        peer.send("incoming", 0).unwrap();
+        assert_eq!(r#"received: ["incoming"]"#, next_line());
+
+        commands.send(parse_chat_command("second".to_string())).unwrap();
+        input.send("", 0).unwrap();
+        assert_eq!("sent: second", next_line());
 
+        drop(commands);
+        input.send("", 0).unwrap();
+        background.join().unwrap();
+    }
+
+    #[test]
+    fn removing_an_endpoint_keeps_it_under_the_other_association() {
+        let mut chat = Chat::new(&SocketParameters {
+            address: "tcp://127.0.0.1:5593".into(),
+            socket_type: SocketType::PAIR,
+            association_type: AssociationType::Bind,
+            ..Default::default()
+        }).unwrap();
+        chat.add_endpoint(AssociationType::Connect, "tcp://127.0.0.1:5593").unwrap();
 
+        chat.remove_endpoint(AssociationType::Connect, "tcp://127.0.0.1:5593").unwrap();
+        assert!(chat.info().unwrap().contains("endpoints: bind tcp://127.0.0.1:5593\n"));
+    }
+
+    #[test]
+    fn wildcard_bind_is_kept_as_bound() {
+        let mut chat = Chat::new(&SocketParameters {
+            address: "tcp://127.0.0.1:*".into(),
+            socket_type: SocketType::PAIR,
+            association_type: AssociationType::Bind,
+            ..Default::default()
+        }).unwrap();
+        let bound = chat.socket.get_last_endpoint().unwrap().unwrap();
+        assert!(chat.info().unwrap().contains(&format!("endpoints: bind {}\n", bound)));
+
+        chat.remove_endpoint(AssociationType::Bind, &bound).unwrap();
+        assert!(chat.info().unwrap().contains("endpoints: none\n"));
+    }
+}
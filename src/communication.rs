@@ -1,34 +1,428 @@
 
-use std::time::Duration;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
 use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
 use std::thread::sleep;
-use crate::socket::{SocketParameters, create_socket};
+use crate::encoding::Encoding;
+use crate::error::Timeout;
+use crate::monitor::{self, Monitor};
+use crate::output::{JsonLines, OutputFormat, capture_line};
+use crate::proxy::Statistics;
+use crate::responder::{Responder, split_envelope};
+use crate::socket::{SocketParameters, SocketType, StreamPeers, Subscription, associate, configure_socket, create_socket};
 
-pub fn listen(parameters: SocketParameters) -> Result<(), Box<dyn Error>> {
-    println!("Listening {:?}", parameters.address);
+/// When `listen` stops: after `count` messages, after `timeout` without a message
+/// or after `duration` in total. Unset limits never stop it.
+#[derive(Default)]
+pub struct Limits {
+    pub count: Option<u64>,
+    pub timeout: Option<Duration>,
+    pub duration: Option<Duration>,
+}
+
+pub fn listen(parameters: SocketParameters, encoding: Encoding, output: OutputFormat, limits: Limits) -> Result<(), Box<dyn Error>> {
+    eprintln!("Listening {:?}", parameters.addresses().join(", "));
+    let ctx = zmq::Context::new();
+
+    let socket = create_socket(&ctx, &parameters)?;
+
+    let mut lines = JsonLines::new((&parameters.socket_type).into(), &parameters.addresses().join(", "), encoding);
+    let mut peers = StreamPeers::default();
+    let deadline = limits.duration.and_then(deadline);
+    let mut received = 0;
+
+    while limits.count.is_none_or(|count| received < count) {
+        if !wait_for_message(&socket, &limits, deadline)? {
+            return match limits.count {
+                Some(_) => Err(Timeout("before receiving all expected messages").into()),
+                None => Ok(()),
+            };
+        }
+
+        let frames = socket.recv_multipart(0)?;
+        received += 1;
+
+        match (output, &parameters.socket_type) {
+            (OutputFormat::Jsonl, _) => println!("{}", lines.line(&frames)),
+            (OutputFormat::Text, SocketType::STREAM) => {
+                if let Some(event) = peers.event(&frames) {
+                    println!("{}", event.describe(encoding));
+                }
+            }
+            (OutputFormat::Text, socket_type) => {
+                for frame in &frames {
+                    match (socket_type, Subscription::decode(frame)) {
+                        (SocketType::XPUB, Some(subscription)) => println!("{}", subscription),
+                        _ => println!("received: {}", encoding.display(frame)),
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Waits until a message can be received. Returns false once the deadline passes
+/// and fails with `Timeout` when the socket stays idle for longer than allowed.
+fn wait_for_message(socket: &zmq::Socket, limits: &Limits, deadline: Option<Instant>) -> Result<bool, Box<dyn Error>> {
+    let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+    let wait = match (limits.timeout, remaining) {
+        (Some(timeout), Some(remaining)) => Some(timeout.min(remaining)),
+        (timeout, remaining) => timeout.or(remaining),
+    };
+
+    if socket.poll(zmq::POLLIN, wait.map_or(-1, poll_timeout))? > 0 {
+        return Ok(true);
+    }
+
+    match remaining {
+        Some(remaining) if wait == Some(remaining) => Ok(false),
+        _ => Err(Timeout("waiting for a message").into()),
+    }
+}
+
+/// How `send` makes sure a peer is there before sending, as messages sent
+/// before a connection is set up may be dropped
+pub enum Readiness {
+    /// Wait for the monitor to report a connected peer, for at most the given time
+    Monitor(Duration),
+    /// Wait for a matching subscription to arrive, PUB senders only
+    Subscription(Duration),
+    /// Sleep for the given time
+    Delay(Duration),
+}
+
+impl Readiness {
+    pub const VALUES: &'static [&'static str] = &["monitor", "subscription", "delay"];
+}
+
+/// Replies `send` waits for after every message, as REQ sockets require
+pub struct Replies {
+    pub encoding: Encoding,
+    pub output: OutputFormat,
+    /// Fail when a reply does not arrive in time, wait forever if unset
+    pub timeout: Duration,
+}
+
+/// Sends every message (a list of frames) over a single socket, each preceded by the topic if set.
+/// Messages are sent as they come, so the iterator may be fed from a pipe.
+pub fn send<I>(mut parameters: SocketParameters, messages: I, readiness: Readiness, replies: Option<Replies>) -> Result<(), Box<dyn Error>>
+    where I: IntoIterator<Item = Result<Vec<Vec<u8>>, Box<dyn Error>>>
+{
+    eprintln!("Sending to {:?}", parameters.addresses().join(", "));
     let ctx = zmq::Context::new();
 
+    if let Readiness::Subscription(_) = readiness {
+        parameters.socket_type = match parameters.socket_type {
+            SocketType::PUB | SocketType::XPUB => SocketType::XPUB,
+            _ => return Err("Waiting for a subscription is only supported by PUB senders".into()),
+        };
+    }
+
+    // A socket has a single monitor, shared by the readiness check and --monitor
+    let ready_events = match parameters.socket_type {
+        SocketType::STREAM => zmq::SocketEvent::CONNECTED.to_raw() | zmq::SocketEvent::ACCEPTED.to_raw(),
+        _ => zmq::SocketEvent::HANDSHAKE_SUCCEEDED.to_raw(),
+    };
+    let waits_for_monitor = matches!(readiness, Readiness::Monitor(_))
+        && !parameters.addresses().iter().all(|address| address.starts_with("inproc://"));
+
+    let socket = configure_socket(&ctx, &parameters)?;
+    let monitor = match (parameters.monitor, waits_for_monitor) {
+        (true, _) => Some(Monitor::attach(&ctx, &socket, zmq::SocketEvent::ALL.to_raw())?),
+        (false, true) => Some(Monitor::attach(&ctx, &socket, ready_events)?),
+        (false, false) => None,
+    };
+    associate(&socket, &parameters)?;
+
+    match readiness {
+        Readiness::Monitor(timeout) => {
+            if let (Some(monitor), true) = (&monitor, waits_for_monitor) {
+                wait_for_peer(monitor, ready_events, parameters.monitor, timeout)?;
+            }
+        }
+        Readiness::Subscription(timeout) => wait_for_subscription(&socket, parameters.topic.as_deref(), timeout)?,
+        Readiness::Delay(delay) => sleep(delay),
+    }
+
+    if let (Some(monitor), true) = (monitor, parameters.monitor) {
+        monitor.print_events();
+    }
+
+    let encoding = replies.as_ref().map_or(Encoding::default(), |replies| replies.encoding);
+    let mut lines = JsonLines::new((&parameters.socket_type).into(), &parameters.addresses().join(", "), encoding);
+
+    for frames in messages {
+        let frames = frames?;
+
+        if let Some(topic) = parameters.topic.as_deref() {
+            socket.send(topic, zmq::SNDMORE)?
+        }
+
+        socket.send_multipart(frames, 0)?;
+
+        if let Some(replies) = &replies {
+            receive_reply(&socket, replies, &mut lines)?;
+        }
+    }
+    Ok(())
+}
+
+/// Waits for one of the `ready` events, printing all events on the way if asked to
+fn wait_for_peer(monitor: &Monitor, ready: u16, print: bool, timeout: Duration) -> Result<(), Box<dyn Error>> {
+    let deadline = deadline(timeout);
+    loop {
+        let remaining = remaining(deadline);
+        match monitor.next(poll_timeout(remaining))? {
+            Some(event) => {
+                if print {
+                    monitor::print_event(&event);
+                }
+                if event.event & ready != 0 {
+                    return Ok(());
+                }
+            }
+            None if remaining.as_millis() == 0 => return Err(Timeout("waiting for a peer to connect").into()),
+            None => {}
+        }
+    }
+}
+
+/// Waits until a subscriber is interested in the topic, or in anything when there is none.
+/// The socket has to be an XPUB.
+fn wait_for_subscription(socket: &zmq::Socket, topic: Option<&str>, timeout: Duration) -> Result<(), Box<dyn Error>> {
+    let deadline = deadline(timeout);
+    loop {
+        let remaining = remaining(deadline);
+        if socket.poll(zmq::POLLIN, poll_timeout(remaining))? == 0 {
+            if remaining.as_millis() == 0 {
+                return Err(Timeout("waiting for a subscription").into());
+            }
+            continue;
+        }
+        if let Some(Subscription::Subscribe(prefix)) = Subscription::decode(&socket.recv_bytes(0)?) {
+            if topic.is_none_or(|topic| topic.as_bytes().starts_with(prefix)) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Answers every request received on a REP or ROUTER socket
+pub fn serve(parameters: SocketParameters, responder: Responder, encoding: Encoding) -> Result<(), Box<dyn Error>> {
+    if !matches!(parameters.socket_type, SocketType::REP | SocketType::ROUTER) {
+        return Err(format!("Requests are served from REP or ROUTER sockets, not {}", parameters.socket_type).into());
+    }
+
+    eprintln!("Serving {:?}", parameters.addresses().join(", "));
+    let ctx = zmq::Context::new();
     let socket = create_socket(&ctx, &parameters)?;
 
     loop {
-        let msg = socket.recv_msg(0)?;
-        println!("received: {:?}", msg.as_str().unwrap());
+        let frames = socket.recv_multipart(0)?;
+        let (envelope, request) = match parameters.socket_type {
+            SocketType::ROUTER => split_envelope(frames),
+            _ => (Vec::new(), frames),
+        };
+
+        for frame in &request {
+            println!("received: {}", encoding.display(frame));
+        }
+
+        let reply = responder.respond(&request)?;
+        socket.send_multipart(envelope.into_iter().chain(reply), 0)?;
+    }
+}
+
+/// Where the proxy copies the messages it forwards
+#[derive(Default)]
+pub struct Capture<'a> {
+    /// PUB or PUSH socket publishing every message
+    pub socket: Option<SocketParameters<'a>>,
+    /// File recording every message as a JSON line
+    pub file: Option<&'a str>,
+}
+
+/// Forwards messages between the two sockets in both directions until interrupted,
+/// or until told to terminate on the control socket
+pub fn proxy<'a>(mut frontend: SocketParameters<'a>, mut backend: SocketParameters<'a>, capture: Capture, control: Option<SocketParameters>) -> Result<(), Box<dyn Error>> {
+    eprintln!("Proxying {:?} to {:?}", frontend.addresses().join(", "), backend.addresses().join(", "));
+    // An XSUB side only forwards the subscriptions coming from the other side
+    for parameters in [&mut frontend, &mut backend] {
+        if let SocketType::XSUB = parameters.socket_type {
+            parameters.topic = None;
+        }
+    }
+    let ctx = zmq::Context::new();
+    let mut frontend = create_socket(&ctx, &frontend)?;
+    let mut backend = create_socket(&ctx, &backend)?;
+    let capture_socket = capture.socket.as_ref().map(|parameters| create_socket(&ctx, parameters)).transpose()?;
+    let control = control.as_ref().map(|parameters| create_socket(&ctx, parameters)).transpose()?;
+
+    match (capture_socket, capture.file, control) {
+        (capture_socket, Some(path), control) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| format!("Cannot open capture file {}: {}", path, e))?;
+            forward(&frontend, &backend, capture_socket.as_ref(), file, control.as_ref())
+        }
+        (Some(mut capture_socket), None, Some(mut control)) =>
+            Ok(zmq::proxy_steerable_with_capture(&mut frontend, &mut backend, &mut capture_socket, &mut control)?),
+        (None, None, Some(mut control)) => Ok(zmq::proxy_steerable(&mut frontend, &mut backend, &mut control)?),
+        (Some(mut capture_socket), None, None) => Ok(zmq::proxy_with_capture(&mut frontend, &mut backend, &mut capture_socket)?),
+        (None, None, None) => Ok(zmq::proxy(&frontend, &backend)?),
+    }
+}
+
+/// Proxy loop used when capturing to a file, as `zmq_proxy` does not tell
+/// in which direction the messages it captures went. Answers the control
+/// socket the way `zmq_proxy_steerable` does.
+fn forward(frontend: &zmq::Socket, backend: &zmq::Socket, capture: Option<&zmq::Socket>, mut file: File,
+           control: Option<&zmq::Socket>) -> Result<(), Box<dyn Error>> {
+    let mut statistics = Statistics::default();
+    let mut paused = false;
+
+    loop {
+        let events = if paused { zmq::PollEvents::empty() } else { zmq::POLLIN };
+        let mut items = vec![frontend.as_poll_item(events), backend.as_poll_item(events)];
+        if let Some(control) = control {
+            items.push(control.as_poll_item(zmq::POLLIN));
+        }
+        zmq::poll(&mut items, -1)?;
+
+        if let (Some(control), Some(item)) = (control, items.get(2)) {
+            if item.is_readable() {
+                match control.recv_bytes(0)?.as_slice() {
+                    b"PAUSE" => paused = true,
+                    b"RESUME" => paused = false,
+                    b"TERMINATE" => {
+                        control.send("", 0)?;
+                        return Ok(());
+                    }
+                    b"STATISTICS" => {
+                        control.send_multipart(statistics.encode(), 0)?;
+                        continue;
+                    }
+                    command => eprintln!("Unknown proxy command {:?}", String::from_utf8_lossy(command)),
+                }
+                control.send("", 0)?;
+                continue;
+            }
+        }
+
+        let directions = [(frontend, backend, "frontend->backend", true), (backend, frontend, "backend->frontend", false)];
+        for (item, &(from, to, direction, from_frontend)) in items.iter().zip(directions.iter()) {
+            if !item.is_readable() {
+                continue;
+            }
+
+            let frames = from.recv_multipart(0)?;
+            statistics.count(from_frontend, frames.iter().map(Vec::len).sum());
+            writeln!(file, "{}", capture_line(direction, &frames))?;
+            if let Some(capture) = capture {
+                capture.send_multipart(frames.iter().map(Vec::as_slice), 0)?;
+            }
+            to.send_multipart(frames, 0)?;
+        }
     }
 }
 
-pub fn send(parameters: SocketParameters, message: &str) -> Result<(), Box<dyn Error>> {
-    println!("Sending to {:?}", parameters.address);
+/// Sends a command to the control socket of a proxy, printing the counters it replies to STATISTICS
+pub fn control_proxy(parameters: SocketParameters, command: &str, timeout: Duration) -> Result<(), Box<dyn Error>> {
     let ctx = zmq::Context::new();
     let socket = create_socket(&ctx, &parameters)?;
+    socket.send(command, 0)?;
 
-    sleep(Duration::from_millis(100));
+    let reply = wait_for_reply(&socket, timeout)?.ok_or(Timeout("waiting for the proxy to answer"))?;
+    if command == "STATISTICS" {
+        println!("{}", Statistics::decode(&reply).ok_or("Invalid reply to STATISTICS")?);
+    }
+    Ok(())
+}
 
-    if let Some(topic) = parameters.topic {
-        socket.send(topic, zmq::SNDMORE)?
+/// Lazy Pirate client: sends every message from a REQ socket and waits for the reply. When none comes
+/// in time, the socket is closed and recreated, connected to the next address, and the request sent again.
+pub fn request<'a, I>(mut parameters: SocketParameters<'a>, addresses: &[Cow<'a, str>], messages: I, replies: Replies, retries: u32) -> Result<(), Box<dyn Error>>
+    where I: IntoIterator<Item = Result<Vec<Vec<u8>>, Box<dyn Error>>>
+{
+    if !matches!(parameters.socket_type, SocketType::REQ) {
+        return Err(format!("Requests are sent from REQ sockets, not {}", parameters.socket_type).into());
     }
 
-    socket.send(message, 0)?;
+    eprintln!("Requesting from {:?}", addresses);
+    let ctx = zmq::Context::new();
+    let mut attempt = 0;
+    parameters.address = addresses[0].clone();
+    let mut socket = create_socket(&ctx, &parameters)?;
+    let mut lines = JsonLines::new((&parameters.socket_type).into(), &parameters.address, replies.encoding);
+
+    for frames in messages {
+        let frames = frames?;
+        let mut retries_left = retries;
+
+        loop {
+            socket.send_multipart(frames.iter().map(Vec::as_slice), 0)?;
+            if let Some(reply) = wait_for_reply(&socket, replies.timeout)? {
+                print_reply(&reply, &replies, &mut lines);
+                break;
+            }
+
+            // The unanswered request would otherwise keep the context from terminating
+            socket.set_linger(0)?;
+            if retries_left == 0 {
+                return Err(Timeout("waiting for a reply, no retries left").into());
+            }
+            retries_left -= 1;
+            attempt += 1;
+            parameters.address = addresses[attempt % addresses.len()].clone();
+            eprintln!("No reply, retrying with {:?} ({} retries left)", parameters.address, retries_left);
+            socket = create_socket(&ctx, &parameters)?;
+        }
+    }
     Ok(())
 }
 
+fn receive_reply(socket: &zmq::Socket, replies: &Replies, lines: &mut JsonLines) -> Result<(), Box<dyn Error>> {
+    let reply = wait_for_reply(socket, replies.timeout)?.ok_or(Timeout("waiting for a reply"))?;
+    print_reply(&reply, replies, lines);
+    Ok(())
+}
 
+/// Receives a reply, or nothing when it does not arrive in time
+fn wait_for_reply(socket: &zmq::Socket, timeout: Duration) -> Result<Option<Vec<Vec<u8>>>, Box<dyn Error>> {
+    if socket.poll(zmq::POLLIN, poll_timeout(timeout))? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(socket.recv_multipart(0)?))
+}
+
+/// When the timeout runs out, none for timeouts too long to ever run out
+fn deadline(timeout: Duration) -> Option<Instant> {
+    Instant::now().checked_add(timeout)
+}
+
+fn remaining(deadline: Option<Instant>) -> Duration {
+    deadline.map_or(Duration::MAX, |deadline| deadline.saturating_duration_since(Instant::now()))
+}
+
+/// Milliseconds to poll for, saturating instead of wrapping around to a negative, that is endless, poll
+fn poll_timeout(timeout: Duration) -> i64 {
+    i64::try_from(timeout.as_millis()).unwrap_or(i64::MAX)
+}
+
+fn print_reply(reply: &[Vec<u8>], replies: &Replies, lines: &mut JsonLines) {
+    match replies.output {
+        OutputFormat::Jsonl => println!("{}", lines.line(reply)),
+        OutputFormat::Text => {
+            for frame in reply {
+                println!("received: {}", replies.encoding.display(frame));
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::error::Error;
+use std::fmt::Write;
+
+/// How message payloads are typed in and shown
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Encoding {
+    #[default]
+    Text,
+    Hex,
+    Base64,
+    Escaped,
+    /// Text for valid UTF-8 frames and `0x` prefixed hex otherwise
+    Auto,
+}
+
+impl Encoding {
+    pub const VALUES: &'static [&'static str] = &["text", "hex", "base64", "escaped", "auto"];
+
+    /// Resolves `Auto` to the encoding actually used for the frame
+    pub fn resolve(self, frame: &[u8]) -> Encoding {
+        match self {
+            Encoding::Auto if std::str::from_utf8(frame).is_ok() => Encoding::Text,
+            Encoding::Auto => Encoding::Hex,
+            encoding => encoding,
+        }
+    }
+
+    pub fn encode(self, frame: &[u8]) -> String {
+        match self.resolve(frame) {
+            Encoding::Hex if self == Encoding::Auto => format!("0x{}", Encoding::Hex.encode(frame)),
+            Encoding::Hex => frame.iter().fold(String::new(), |mut hex, b| {
+                let _ = write!(hex, "{:02x}", b);
+                hex
+            }),
+            Encoding::Base64 => base64::encode(frame),
+            Encoding::Escaped => escape(frame),
+            _ => String::from_utf8_lossy(frame).to_string(),
+        }
+    }
+
+    /// Encodes the frame for printing, text is quoted so it cannot be confused with the other encodings
+    pub fn display(self, frame: &[u8]) -> String {
+        match self.resolve(frame) {
+            Encoding::Text => format!("{:?}", self.encode(frame)),
+            Encoding::Escaped => format!("\"{}\"", self.encode(frame)),
+            _ => self.encode(frame),
+        }
+    }
+
+    pub fn decode(self, input: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        match self {
+            Encoding::Text => Ok(input.as_bytes().to_vec()),
+            Encoding::Hex => decode_hex(input),
+            Encoding::Base64 => base64::decode(input.trim()).map_err(|e| format!("Invalid base64: {}", e).into()),
+            Encoding::Escaped => unescape(input),
+            Encoding::Auto => match input.strip_prefix("0x") {
+                Some(hex) => decode_hex(hex),
+                None => Ok(input.as_bytes().to_vec()),
+            },
+        }
+    }
+}
+
+impl std::convert::From<&str> for Encoding {
+    fn from(s: &str) -> Self {
+        match s {
+            "hex" => Encoding::Hex,
+            "base64" => Encoding::Base64,
+            "escaped" => Encoding::Escaped,
+            "auto" => Encoding::Auto,
+            _ => Encoding::Text,
+        }
+    }
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Encoding::Text => "text",
+            Encoding::Hex => "hex",
+            Encoding::Base64 => "base64",
+            Encoding::Escaped => "escaped",
+            Encoding::Auto => "auto",
+        })
+    }
+}
+
+fn decode_hex(input: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let digits = input.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    if !digits.len().is_multiple_of(2) || !digits.is_ascii() {
+        return Err(format!("Invalid hex {:?}, expected pairs of hex digits", input).into());
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16)
+            .map_err(|_| format!("Invalid hex {:?}, expected pairs of hex digits", input).into()))
+        .collect()
+}
+
+fn escape(frame: &[u8]) -> String {
+    frame.iter().fold(String::new(), |mut escaped, &b| {
+        match b {
+            b'\n' => escaped.push_str("\\n"),
+            b'\r' => escaped.push_str("\\r"),
+            b'\t' => escaped.push_str("\\t"),
+            b'\\' => escaped.push_str("\\\\"),
+            b'"' => escaped.push_str("\\\""),
+            0x20..=0x7e => escaped.push(b as char),
+            _ => {
+                let _ = write!(escaped, "\\x{:02x}", b);
+            }
+        }
+        escaped
+    })
+}
+
+fn unescape(input: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut result = Vec::new();
+    let mut bytes = input.bytes();
+
+    while let Some(b) = bytes.next() {
+        if b != b'\\' {
+            result.push(b);
+            continue;
+        }
+        match bytes.next() {
+            Some(b'n') => result.push(b'\n'),
+            Some(b'r') => result.push(b'\r'),
+            Some(b't') => result.push(b'\t'),
+            Some(b'0') => result.push(0),
+            Some(b'\\') => result.push(b'\\'),
+            Some(b'"') => result.push(b'"'),
+            Some(b'x') => {
+                let hex = [bytes.next(), bytes.next()];
+                match hex {
+                    [Some(high), Some(low)] => result.extend(decode_hex(&String::from_utf8_lossy(&[high, low]))?),
+                    _ => return Err(format!("Incomplete \\x escape in {:?}", input).into()),
+                }
+            }
+            Some(other) => return Err(format!("Unknown escape \\{} in {:?}", other as char, input).into()),
+            None => return Err(format!("Trailing backslash in {:?}", input).into()),
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encoding_frames() {
+        let binary = [0x08, 0x96, 0x01, b'a'];
+
+        assert_eq!("08960161", Encoding::Hex.encode(&binary));
+        assert_eq!("CJYBYQ==", Encoding::Base64.encode(&binary));
+        assert_eq!("\\x08\\x96\\x01a", Encoding::Escaped.encode(&binary));
+        assert_eq!("0x08960161", Encoding::Auto.encode(&binary));
+        assert_eq!("0x08960161", Encoding::Auto.display(&binary));
+        assert_eq!("\"text\"", Encoding::Auto.display(b"text"));
+        assert_eq!("\"a\\\"b\\n\"", Encoding::Escaped.display(b"a\"b\n"));
+        assert_eq!(Encoding::Hex, Encoding::Auto.resolve(&binary));
+        assert_eq!(Encoding::Text, Encoding::Auto.resolve(b"text"));
+    }
+
+    #[test]
+    fn decoding_input() {
+        let binary = vec![0x08, 0x96, 0x01, b'a'];
+
+        assert_eq!(binary, Encoding::Hex.decode("08960161").unwrap());
+        assert_eq!(binary, Encoding::Hex.decode("08 96 01 61").unwrap());
+        assert_eq!(binary, Encoding::Base64.decode("CJYBYQ==").unwrap());
+        assert_eq!(binary, Encoding::Escaped.decode("\\x08\\x96\\x01a").unwrap());
+        assert_eq!(binary, Encoding::Auto.decode("0x08960161").unwrap());
+        assert_eq!(b"text".to_vec(), Encoding::Auto.decode("text").unwrap());
+        assert_eq!(b"a\r\n".to_vec(), Encoding::Escaped.decode("a\\r\\n").unwrap());
+
+        assert!(Encoding::Hex.decode("089").is_err());
+        assert!(Encoding::Hex.decode("zz").is_err());
+        assert!(Encoding::Base64.decode("!").is_err());
+        assert!(Encoding::Escaped.decode("\\q").is_err());
+        assert!(Encoding::Escaped.decode("\\x1").is_err());
+    }
+}
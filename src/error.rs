@@ -0,0 +1,46 @@
+use std::error::Error;
+
+/// Exit code when the expected messages did not arrive in time
+pub const EXIT_TIMEOUT: i32 = 2;
+/// Exit code when ZeroMQ reported an error
+pub const EXIT_SOCKET_ERROR: i32 = 3;
+/// Exit code for all other errors, e.g. invalid configuration
+pub const EXIT_ERROR: i32 = 1;
+
+#[derive(Debug)]
+pub struct Timeout(pub &'static str);
+
+impl std::fmt::Display for Timeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Timed out {}", self.0)
+    }
+}
+
+impl Error for Timeout {}
+
+pub fn exit_code(error: &(dyn Error + 'static)) -> i32 {
+    if error.is::<Timeout>() {
+        EXIT_TIMEOUT
+    } else if error.is::<zmq::Error>() {
+        EXIT_SOCKET_ERROR
+    } else {
+        EXIT_ERROR
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exit_codes() {
+        let timeout: Box<dyn Error> = Box::new(Timeout("waiting for messages"));
+        let socket: Box<dyn Error> = Box::new(zmq::Error::EADDRINUSE);
+        let other: Box<dyn Error> = "Incorrect address".into();
+
+        assert_eq!(EXIT_TIMEOUT, exit_code(timeout.as_ref()));
+        assert_eq!(EXIT_SOCKET_ERROR, exit_code(socket.as_ref()));
+        assert_eq!(EXIT_ERROR, exit_code(other.as_ref()));
+        assert_eq!("Timed out waiting for messages", timeout.to_string());
+    }
+}
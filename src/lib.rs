@@ -1,3 +1,13 @@
 pub mod chat;
+pub mod encoding;
+pub mod error;
+pub mod monitor;
+pub mod options;
+pub mod output;
+pub mod profile;
+pub mod proxy;
+pub mod responder;
+pub mod security;
 pub mod socket;
-pub mod validation;
\ No newline at end of file
+pub mod validation;
+pub mod zap;
\ No newline at end of file
@@ -1,45 +1,564 @@
 mod communication;
-use rzmq::{chat, socket, validation};
-use clap::{App, SubCommand, AppSettings, Arg, ArgMatches};
+use rzmq::{chat, encoding, error, monitor, output, profile, proxy, responder, security, socket, validation};
+use responder::Responder;
+use encoding::Encoding;
+use output::OutputFormat;
+use rzmq::options::SocketOptions;
+use clap::{App, SubCommand, AppSettings, Arg, ArgGroup, ArgMatches};
 use communication::*;
 use socket::{AssociationType, SocketType, SocketParameters};
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::iter;
 
 fn set_common_socket_args<'a, 'b>(subcommand: App<'a, 'b>, socket_types: &[&'static str]) -> App<'a, 'b> {
-    subcommand.arg(Arg::with_name("address")
+    set_socket_args(subcommand.arg(address_arg()), socket_types)
+}
+
+fn address_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("address")
         .long("address")
         .short("a")
         .takes_value(true)
-        .required_unless("config")
-        .validator(validation::validate_socket))
-        .arg(Arg::with_name("socket type")
+        .required_unless_one(&["config", "profile", "bind", "connect"])
+        .validator(validation::validate_socket)
+}
+
+/// Socket arguments besides `--address`, for subcommands taking it differently
+fn set_socket_args<'a, 'b>(subcommand: App<'a, 'b>, socket_types: &[&'static str]) -> App<'a, 'b> {
+    subcommand.arg(Arg::with_name("socket type")
             .long("type")
             .possible_values(socket_types)
             .default_value(socket_types[0]))
         .arg(Arg::with_name("socket id")
             .long("id")
             .takes_value(true))
-        .arg(Arg::with_name("bind").long("bind").conflicts_with("connect"))
-        .arg(Arg::with_name("connect").long("connect"))
+        .arg(Arg::with_name("bind")
+            .long("bind")
+            .takes_value(true)
+            .multiple(true)
+            .min_values(0)
+            .validator(validation::validate_socket)
+            .help("Bind --address, or bind the given endpoints too"))
+        .arg(Arg::with_name("connect")
+            .long("connect")
+            .takes_value(true)
+            .multiple(true)
+            .min_values(0)
+            .validator(validation::validate_socket)
+            .help("Connect to --address, or connect to the given endpoints too"))
         .arg(Arg::with_name("config").long("config").short("c").takes_value(true))
+        .arg(Arg::with_name("profile").long("profile").short("p").takes_value(true).conflicts_with("config"))
+        .args(&socket_option_args())
+        .arg(monitor_arg())
+        .arg(Arg::with_name("endpoint file")
+            .long("endpoint-file")
+            .takes_value(true)
+            .help("Write the bound endpoints to this file, one per line, e.g. to learn the port bound for tcp://127.0.0.1:*"))
+        .arg(Arg::with_name("curve server").long("curve-server").help("Act as a CURVE server"))
+        .arg(Arg::with_name("curve secret key")
+            .long("curve-secret-key")
+            .takes_value(true)
+            .help("Own secret key, in Z85 or a certificate file"))
+        .arg(Arg::with_name("curve public key")
+            .long("curve-public-key")
+            .takes_value(true)
+            .help("Own public key, in Z85 or a certificate file"))
+        .arg(Arg::with_name("curve server key")
+            .long("curve-server-key")
+            .takes_value(true)
+            .conflicts_with("curve server")
+            .help("Public key of the CURVE server, in Z85 or a certificate file"))
+        .arg(Arg::with_name("plain server").long("plain-server").help("Act as a PLAIN server"))
+        .arg(Arg::with_name("plain username")
+            .long("plain-username")
+            .takes_value(true)
+            .conflicts_with("plain server"))
+        .arg(Arg::with_name("plain password")
+            .long("plain-password")
+            .takes_value(true)
+            .conflicts_with("plain server"))
+        .arg(Arg::with_name("zap users")
+            .long("zap-users")
+            .takes_value(true)
+            .help("Authenticate PLAIN clients against a file of username=password lines"))
+        .arg(Arg::with_name("zap allow")
+            .long("zap-allow")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Accept peers only from this IP address"))
+        .arg(Arg::with_name("zap deny")
+            .long("zap-deny")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Reject peers from this IP address"))
+        .arg(Arg::with_name("zap curve certs")
+            .long("zap-curve-certs")
+            .takes_value(true)
+            .help("Accept only CURVE clients with a public certificate in this directory"))
+}
+
+fn monitor_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("monitor")
+        .long("monitor")
+        .help("Print socket events, e.g. CONNECTED or DISCONNECTED, with their endpoint and time")
+}
+
+fn encoding_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("encoding")
+        .long("encoding")
+        .short("e")
+        .possible_values(Encoding::VALUES)
+        .default_value("text")
+        .help("Encoding of message payloads")
+}
+
+/// Arguments selecting the messages to send, read by `messages`
+fn message_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("message")
+            .long("message")
+            .short("m")
+            .takes_value(true)
+            .required_unless_one(&["frame", "file", "stdin"])
+            .conflicts_with_all(&["frame", "file", "stdin"])
+            .multiple(true),
+        Arg::with_name("frame")
+            .long("frame")
+            .short("f")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .conflicts_with_all(&["file", "stdin"])
+            .help("Frame of a multipart message, may be repeated and empty"),
+        Arg::with_name("file")
+            .long("file")
+            .takes_value(true)
+            .conflicts_with("stdin")
+            .help("Send the file contents as a single message"),
+        Arg::with_name("stdin")
+            .long("stdin")
+            .help("Send standard input read until EOF as a single message"),
+        Arg::with_name("line delimited")
+            .long("line-delimited")
+            .requires("stdin")
+            .help("Send every line of standard input as a separate message"),
+        encoding_arg(),
+    ]
+}
+
+fn socket_option_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    let values = [
+        ("sndhwm", "High water mark for outbound messages"),
+        ("rcvhwm", "High water mark for inbound messages"),
+        ("linger", "Linger period for pending messages on close [ms]"),
+        ("rcvtimeo", "Receive timeout [ms]"),
+        ("sndtimeo", "Send timeout [ms]"),
+        ("reconnect-ivl", "Reconnection interval [ms]"),
+        ("reconnect-ivl-max", "Maximum reconnection interval [ms]"),
+        ("backlog", "Maximum length of the queue of pending connections"),
+        ("maxmsgsize", "Maximum acceptable inbound message size [bytes]"),
+        ("tcp-keepalive", "Override SO_KEEPALIVE (-1 leaves the OS default)"),
+        ("tcp-keepalive-cnt", "Override TCP_KEEPCNT"),
+        ("tcp-keepalive-idle", "Override TCP_KEEPIDLE"),
+        ("tcp-keepalive-intvl", "Override TCP_KEEPINTVL"),
+        ("affinity", "I/O thread affinity bitmask"),
+    ];
+    let flags = [
+        ("immediate", "Queue messages only to completed connections"),
+        ("conflate", "Keep only the last message"),
+        ("ipv6", "Enable IPv6"),
+    ];
+
+    values.iter()
+        .map(|(name, help)| Arg::with_name(name)
+            .long(name)
+            .takes_value(true)
+            .validator(validation::validate_number)
+            .help(help))
+        .chain(flags.iter().map(|(name, help)| Arg::with_name(name).long(name).help(help)))
+        .collect()
+}
+
+fn override_option<T: std::str::FromStr>(matches: &ArgMatches, name: &str, option: &mut Option<T>) -> Result<(), Box<dyn Error>> {
+    if let Some(value) = matches.value_of(name) {
+        *option = Some(value.parse().map_err(|_| format!("Invalid value for --{}: {}", name, value))?);
+    }
+    Ok(())
+}
+
+fn extract_socket_options(matches: &ArgMatches, options: &mut SocketOptions) -> Result<(), Box<dyn Error>> {
+    override_option(matches, "sndhwm", &mut options.sndhwm)?;
+    override_option(matches, "rcvhwm", &mut options.rcvhwm)?;
+    override_option(matches, "linger", &mut options.linger)?;
+    override_option(matches, "rcvtimeo", &mut options.rcvtimeo)?;
+    override_option(matches, "sndtimeo", &mut options.sndtimeo)?;
+    override_option(matches, "reconnect-ivl", &mut options.reconnect_ivl)?;
+    override_option(matches, "reconnect-ivl-max", &mut options.reconnect_ivl_max)?;
+    override_option(matches, "backlog", &mut options.backlog)?;
+    override_option(matches, "maxmsgsize", &mut options.maxmsgsize)?;
+    override_option(matches, "tcp-keepalive", &mut options.tcp_keepalive)?;
+    override_option(matches, "tcp-keepalive-cnt", &mut options.tcp_keepalive_cnt)?;
+    override_option(matches, "tcp-keepalive-idle", &mut options.tcp_keepalive_idle)?;
+    override_option(matches, "tcp-keepalive-intvl", &mut options.tcp_keepalive_intvl)?;
+    override_option(matches, "affinity", &mut options.affinity)?;
+
+    if matches.is_present("immediate") {
+        options.immediate = Some(true);
+    }
+    if matches.is_present("conflate") {
+        options.conflate = Some(true);
+    }
+    if matches.is_present("ipv6") {
+        options.ipv6 = Some(true);
+    }
+    Ok(())
+}
+
+/// Reads the config file, or the profiles file when a profile is selected
+fn read_config(matches: &ArgMatches) -> Result<Option<String>, Box<dyn Error>> {
+    if matches.is_present("profile") {
+        return profile::read().map(Some);
+    }
+
+    match matches.value_of("config") {
+        Some(path) => fs::read_to_string(path)
+            .map(Some)
+            .map_err(|e| format!("Cannot read config file {}: {}", path, e).into()),
+        None => Ok(None)
+    }
+}
+
+/// Builds socket parameters from the config file or profile (if any) and the command line.
+/// Options given explicitly on the command line take precedence over the file.
+fn extract_common_parameters<'a>(matches: &'a ArgMatches, config: Option<&'a str>) -> Result<SocketParameters<'a>, Box<dyn Error>> {
+    let mut parameters = match (config, matches.value_of("profile")) {
+        (Some(profiles), Some(name)) => profile::find(profiles, name)?,
+        (Some(json), None) => socket::parse(json)?,
+        (None, _) => SocketParameters {
+            socket_type: matches.value_of("socket type").unwrap().into(),
+            ..SocketParameters::default()
+        }
+    };
+
+    if let Some(address) = matches.value_of("address") {
+        parameters.address = address.into();
+    }
+    let bind = matches.values_of("bind").map(Iterator::collect::<Vec<_>>).unwrap_or_default();
+    let connect = matches.values_of("connect").map(Iterator::collect::<Vec<_>>).unwrap_or_default();
+    parameters.bind.extend(bind.iter().copied().map(Cow::from));
+    parameters.connect.extend(connect.iter().copied().map(Cow::from));
+
+    let addresses = parameters.addresses();
+    if addresses.is_empty() {
+        return Err("No endpoint given, use --address, --bind or --connect".into());
+    }
+    for address in addresses {
+        validation::validate_socket(address.to_string())?;
+    }
+
+    if matches.occurrences_of("socket type") > 0 {
+        parameters.socket_type = matches.value_of("socket type").unwrap().into();
+    }
+
+    // Given without endpoints, --bind and --connect tell what to do with --address
+    if matches.is_present("bind") && bind.is_empty() && matches.is_present("connect") && connect.is_empty() {
+        return Err("Give either --bind or --connect for --address, not both".into());
+    }
+    if matches.is_present("bind") && bind.is_empty() {
+        parameters.association_type = AssociationType::Bind;
+    } else if matches.is_present("connect") && connect.is_empty() {
+        parameters.association_type = AssociationType::Connect;
+    } else if config.is_none() {
+        parameters.association_type = parameters.socket_type.default_association();
+    }
+
+    if let Some(id) = matches.value_of("socket id") {
+        parameters.socket_id = Some(id.into());
+    }
+
+    if let Some(topic) = matches.value_of("topic") {
+        parameters.topic = Some(topic.into());
+    }
+
+    extract_socket_options(matches, &mut parameters.options)?;
+
+    if matches.is_present("monitor") {
+        parameters.monitor = true;
+    }
+
+    if let Some(path) = matches.value_of("endpoint file") {
+        parameters.endpoint_file = Some(path.into());
+    }
+
+    if matches.is_present("curve server") {
+        parameters.curve.server = true;
+    }
+    if let Some(key) = matches.value_of("curve secret key") {
+        parameters.curve.secret_key = Some(key.into());
+    }
+    if let Some(key) = matches.value_of("curve public key") {
+        parameters.curve.public_key = Some(key.into());
+    }
+    if let Some(key) = matches.value_of("curve server key") {
+        parameters.curve.server_key = Some(key.into());
+    }
+
+    if matches.is_present("plain server") {
+        parameters.plain.server = true;
+    }
+    if let Some(username) = matches.value_of("plain username") {
+        parameters.plain.username = Some(username.into());
+    }
+    if let Some(password) = matches.value_of("plain password") {
+        parameters.plain.password = Some(password.into());
+    }
+
+    if let Some(users) = matches.value_of("zap users") {
+        parameters.zap.users = Some(users.into());
+    }
+    if let Some(allow) = matches.values_of("zap allow") {
+        parameters.zap.allow = allow.map(Cow::from).collect();
+    }
+    if let Some(deny) = matches.values_of("zap deny") {
+        parameters.zap.deny = deny.map(Cow::from).collect();
+    }
+    if let Some(dir) = matches.value_of("zap curve certs") {
+        parameters.zap.curve_certs = Some(dir.into());
+    }
+
+    Ok(parameters)
 }
 
-fn extract_common_parameters<'a>(matches: &'a ArgMatches) -> SocketParameters<'a> {
-    let socket_type: SocketType = matches.value_of("socket type").unwrap().into();
+type Messages<'a> = Box<dyn Iterator<Item = Result<Vec<Vec<u8>>, Box<dyn Error>>> + 'a>;
+
+/// Messages to send, taken from the command line, a file or standard input.
+/// Payloads typed in as text are decoded, file and stdin contents are sent as they are
+/// unless standard input is read line by line.
+fn messages<'a>(matches: &'a ArgMatches) -> Result<Messages<'a>, Box<dyn Error>> {
+    let encoding: Encoding = matches.value_of("encoding").unwrap().into();
+
+    if let Some(path) = matches.value_of("file") {
+        let content = fs::read(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+        return Ok(Box::new(iter::once(Ok(vec![content]))));
+    }
+
+    if matches.is_present("line delimited") {
+        return Ok(Box::new(io::stdin().lock().lines().map(move |line| Ok(vec![encoding.decode(&line?)?]))));
+    }
+
+    if matches.is_present("stdin") {
+        let mut content = Vec::new();
+        io::stdin().read_to_end(&mut content)?;
+        return Ok(Box::new(iter::once(Ok(vec![content]))));
+    }
 
-    let a = if matches.is_present("bind") {
-        AssociationType::Bind
-    } else if matches.is_present("connect") {
-        AssociationType::Connect
-    } else {
-        socket_type.default_association()
+    let frames = match matches.values_of("frame") {
+        Some(frames) => frames.map(|frame| encoding.decode(frame)).collect::<Result<Vec<_>, _>>()?,
+        None => vec![encoding.decode(&matches.values_of("message").unwrap().collect::<Vec<_>>().join(" "))?],
     };
+    Ok(Box::new(iter::once(Ok(frames))))
+}
+
+/// How to wait for a peer before sending. A connected subscriber is not necessarily subscribed yet,
+/// so PUB senders wait for a subscription unless told otherwise.
+fn readiness(matches: &ArgMatches, socket_type: &SocketType) -> Result<Readiness, Box<dyn Error>> {
+    let timeout = validation::parse_duration(matches.value_of("ready timeout").unwrap())?;
+    Ok(match (matches.value_of("ready"), socket_type) {
+        (Some("subscription"), _) | (None, SocketType::PUB) | (None, SocketType::XPUB) => Readiness::Subscription(timeout),
+        (Some("delay"), _) => Readiness::Delay(validation::parse_duration(matches.value_of("delay").unwrap())?),
+        _ => Readiness::Monitor(timeout),
+    })
+}
+
+fn responder(matches: &ArgMatches) -> Result<Responder, Box<dyn Error>> {
+    if let Some(path) = matches.value_of("reply file") {
+        return Ok(Responder::Fixed(fs::read(path).map_err(|e| format!("Cannot read {}: {}", path, e))?));
+    }
+    if let Some(template) = matches.value_of("reply template") {
+        return Ok(Responder::Template(template.to_string()));
+    }
+    if let Some(command) = matches.value_of("exec") {
+        return Ok(Responder::Exec(command.to_string()));
+    }
+    Ok(Responder::Echo)
+}
+
+fn validate_socket_spec(spec: String) -> Result<(), String> {
+    socket::parse_spec(&spec).map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn run(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    match matches.subcommand() {
+        ("send", Some(matches)) => {
+            let config = read_config(matches)?;
+            let parameters = extract_common_parameters(matches, config.as_deref())?;
+            let readiness = readiness(matches, &parameters.socket_type)?;
+            let expects_reply = match parameters.socket_type {
+                SocketType::REQ => true,
+                SocketType::DEALER => matches.is_present("expect reply"),
+                _ => false,
+            };
+            let replies = if expects_reply {
+                Some(Replies {
+                    encoding: matches.value_of("encoding").unwrap().into(),
+                    output: matches.value_of("output").unwrap().into(),
+                    timeout: validation::parse_duration(matches.value_of("timeout").unwrap())?,
+                })
+            } else {
+                None
+            };
+            send(parameters, messages(matches)?, readiness, replies)
+        }
+        ("request", Some(matches)) => {
+            let config = read_config(matches)?;
+            let parameters = extract_common_parameters(matches, config.as_deref())?;
+            // Every retry recreates the socket for the next address, further endpoints would never rotate
+            if !parameters.bind.is_empty() || !parameters.connect.is_empty() {
+                return Err("Requests rotate through --address, give every endpoint with --address instead of --bind or --connect".into());
+            }
+            let addresses = match matches.values_of("address") {
+                Some(addresses) => addresses.map(Cow::from).collect(),
+                None => vec![parameters.address.clone()],
+            };
+            let replies = Replies {
+                encoding: matches.value_of("encoding").unwrap().into(),
+                output: matches.value_of("output").unwrap().into(),
+                timeout: validation::parse_duration(matches.value_of("timeout").unwrap())?,
+            };
+            request(parameters, &addresses, messages(matches)?, replies, matches.value_of("retries").unwrap().parse()?)
+        }
+        ("serve", Some(matches)) => {
+            let config = read_config(matches)?;
+            let parameters = extract_common_parameters(matches, config.as_deref())?;
+            serve(parameters, responder(matches)?, matches.value_of("encoding").unwrap().into())
+        }
+        ("proxy", Some(matches)) => {
+            let capture = Capture {
+                socket: matches.value_of("capture").map(|address| SocketParameters {
+                    address: address.into(),
+                    socket_type: matches.value_of("capture type").unwrap().into(),
+                    association_type: AssociationType::Bind,
+                    ..SocketParameters::default()
+                }),
+                file: matches.value_of("capture file"),
+            };
+            let control = matches.value_of("control").map(|address| SocketParameters {
+                address: address.into(),
+                socket_type: SocketType::REP,
+                association_type: AssociationType::Bind,
+                ..SocketParameters::default()
+            });
+            let mut frontend = socket::parse_spec(matches.value_of("frontend").unwrap())?;
+            let mut backend = socket::parse_spec(matches.value_of("backend").unwrap())?;
+            if matches.is_present("monitor") {
+                frontend.monitor = true;
+                backend.monitor = true;
+            }
+            communication::proxy(frontend, backend, capture, control)
+        }
+        ("proxy-ctl", Some(matches)) => {
+            let mut parameters = SocketParameters {
+                address: matches.value_of("address").unwrap().into(),
+                socket_type: SocketType::REQ,
+                association_type: AssociationType::Connect,
+                monitor: matches.is_present("monitor"),
+                ..SocketParameters::default()
+            };
+            parameters.options.linger = Some(0);
+            control_proxy(parameters,
+                          &matches.value_of("command").unwrap().to_uppercase(),
+                          validation::parse_duration(matches.value_of("timeout").unwrap())?)
+        }
+        ("listen", Some(matches)) => {
+            let config = read_config(matches)?;
+            let parameters = extract_common_parameters(matches, config.as_deref())?;
+            let limits = Limits {
+                count: matches.value_of("count").map(str::parse).transpose()?,
+                timeout: matches.value_of("timeout").map(validation::parse_duration).transpose()?,
+                duration: matches.value_of("duration").map(validation::parse_duration).transpose()?,
+            };
+            listen(parameters,
+                   matches.value_of("encoding").unwrap().into(),
+                   matches.value_of("output").unwrap().into(),
+                   limits)
+        }
+        ("chat", Some(matches)) => {
+            let config = read_config(matches)?;
+            let parameters = extract_common_parameters(matches, config.as_deref())?;
+            chat::chat(parameters, matches.value_of("encoding").unwrap().into())
+        }
+        ("profiles", Some(matches)) => profiles(matches),
+        ("keygen", Some(matches)) => keygen(matches),
+        _ => Ok(())
+    }
+}
+
+/// Generates a CURVE keypair, saving it as czmq certificates `<path>` and `<path>_secret`
+/// or printing it when no path is given
+fn keygen(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let keypair = zmq::CurveKeyPair::new()?;
+
+    match matches.value_of("path") {
+        Some(path) => {
+            let (public, secret) = security::certificates(&keypair)?;
+            let secret_path = format!("{}_secret", path);
+            // Readable by the owner only from the start, never overwriting an existing secret
+            let mut options = fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.mode(0o600);
+            }
+            let mut secret_file = options.open(&secret_path)
+                .map_err(|e| format!("Cannot create {}: {}", secret_path, e))?;
+            secret_file.write_all(secret.as_bytes())?;
+            fs::write(path, public)?;
+            println!("Public certificate: {}", path);
+            println!("Secret certificate: {}", secret_path);
+        }
+        None => {
+            println!("public-key = \"{}\"", zmq::z85_encode(&keypair.public_key)?);
+            println!("secret-key = \"{}\"", zmq::z85_encode(&keypair.secret_key)?);
+        }
+    }
+    Ok(())
+}
 
-    SocketParameters {
-        address: matches.value_of("address").unwrap(),
-        socket_type,
-        association_type: a,
-        socket_id: matches.value_of("socket id"),
-        topic: matches.value_of("topic")
+fn profiles(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let content = profile::read()?;
+    let mut profiles = profile::parse(&content)?;
+
+    match matches.subcommand() {
+        ("list", Some(_)) => {
+            for (name, parameters) in &profiles {
+                println!("{}: {} {}", name, parameters.socket_type, parameters.addresses().join(", "));
+            }
+            Ok(())
+        }
+        ("show", Some(matches)) => {
+            let name = matches.value_of("name").unwrap();
+            let parameters = profiles.get(name).ok_or_else(|| format!("No profile named {}", name))?;
+            println!("{}", serde_json::to_string_pretty(parameters)?);
+            Ok(())
+        }
+        ("add", Some(matches)) => {
+            let config = read_config(matches)?;
+            let parameters = extract_common_parameters(matches, config.as_deref())?;
+            profiles.insert(matches.value_of("name").unwrap().into(), parameters);
+            profile::save(&profiles)
+        }
+        ("remove", Some(matches)) => {
+            let name = matches.value_of("name").unwrap();
+            profiles.remove(name).ok_or_else(|| format!("No profile named {}", name))?;
+            profile::save(&profiles)
+        }
+        _ => Ok(())
     }
 }
 
@@ -51,22 +570,124 @@ fn main() {
                                                SocketType::PUSH.into(),
                                                SocketType::PUB.into(),
                                                SocketType::REQ.into(),
+                                               SocketType::DEALER.into(),
                                                SocketType::PAIR.into()])
-            .arg(Arg::with_name("message")
-                .long("message")
-                .short("m")
+            .args(&message_args())
+            .arg(Arg::with_name("ready")
+                .long("ready")
+                .takes_value(true)
+                .possible_values(Readiness::VALUES)
+                .help("Wait for a connected peer (default), for a subscription (default for PUB) or for --delay before sending"))
+            .arg(Arg::with_name("ready timeout")
+                .long("ready-timeout")
+                .takes_value(true)
+                .default_value("5s")
+                .validator(validation::validate_duration)
+                .help("Fail when no peer is ready after this long"))
+            .arg(Arg::with_name("delay")
+                .long("delay")
                 .takes_value(true)
-                .required_unless("config")
-                .multiple(true))
+                .default_value("100ms")
+                .validator(validation::validate_duration)
+                .help("Time to wait before sending with --ready delay"))
+            .arg(Arg::with_name("expect reply")
+                .long("expect-reply")
+                .help("Wait for a reply to every message, always done by REQ"))
+            .arg(Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .possible_values(OutputFormat::VALUES)
+                .default_value("text")
+                .help("Print replies as a line per frame or a JSON object per message"))
+            .arg(Arg::with_name("timeout")
+                .long("timeout")
+                .takes_value(true)
+                .default_value("2500ms")
+                .validator(validation::validate_duration)
+                .help("Fail when a reply does not arrive within this time"))
             .arg(Arg::with_name("topic")
                 .long("topic")
                 .short("t")
                 .takes_value(true)))
+        .subcommand(set_socket_args(SubCommand::with_name("request")
+                                        .arg(address_arg()
+                                            .multiple(true)
+                                            .number_of_values(1)
+                                            .help("Endpoint to send requests to, repeat it to fail over to the next on every retry")),
+                                    &[SocketType::REQ.into()])
+            .about("Sends requests, retrying with a new socket when no reply comes (Lazy Pirate)")
+            .args(&message_args())
+            .arg(Arg::with_name("timeout")
+                .long("timeout")
+                .takes_value(true)
+                .default_value("2500ms")
+                .validator(validation::validate_duration)
+                .help("Time to wait for a reply before retrying"))
+            .arg(Arg::with_name("retries")
+                .long("retries")
+                .takes_value(true)
+                .default_value("3")
+                .validator(validation::validate_number)
+                .help("Retries before giving up on a request"))
+            .arg(Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .possible_values(OutputFormat::VALUES)
+                .default_value("text")
+                .help("Print replies as a line per frame or a JSON object per message")))
+        .subcommand(set_common_socket_args(SubCommand::with_name("serve"),
+                                           &[
+                                               SocketType::REP.into(),
+                                               SocketType::ROUTER.into()])
+            .about("Answers every request, e.g. as a fake backend")
+            .arg(Arg::with_name("echo").long("echo").help("Reply with the request"))
+            .arg(Arg::with_name("reply file")
+                .long("reply-file")
+                .takes_value(true)
+                .help("Reply with the file contents"))
+            .arg(Arg::with_name("reply template")
+                .long("reply-template")
+                .takes_value(true)
+                .help("Reply with {body} replaced by the request body and {0}, {1}... by its frames"))
+            .arg(Arg::with_name("exec")
+                .long("exec")
+                .takes_value(true)
+                .help("Reply with the output of a shell command reading the request body on stdin"))
+            .group(ArgGroup::with_name("responder")
+                .args(&["echo", "reply file", "reply template", "exec"])
+                .required(true))
+            .arg(encoding_arg()))
     .subcommand(set_common_socket_args(SubCommand::with_name("listen"),
                                            &[
                                                SocketType::PULL.into(),
                                                SocketType::SUB.into(),
-                                               SocketType::PAIR.into()])
+                                               SocketType::PAIR.into(),
+                                               SocketType::XPUB.into(),
+                                               SocketType::XSUB.into(),
+                                               SocketType::STREAM.into()])
+            .arg(encoding_arg())
+            .arg(Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .possible_values(OutputFormat::VALUES)
+                .default_value("text")
+                .help("Print a line per frame or a JSON object per message"))
+            .arg(Arg::with_name("count")
+                .long("count")
+                .short("n")
+                .takes_value(true)
+                .validator(validation::validate_number)
+                .help("Exit after receiving this many messages"))
+            .arg(Arg::with_name("timeout")
+                .long("timeout")
+                .takes_value(true)
+                .validator(validation::validate_duration)
+                .help("Fail when no message arrives for this long, e.g. 5s"))
+            .arg(Arg::with_name("duration")
+                .long("duration")
+                .takes_value(true)
+                .validator(validation::validate_duration)
+                .help("Exit after listening this long, fail if --count messages did not arrive"))
             .arg(Arg::with_name("topic")
                 .long("topic")
                 .short("t")
@@ -82,30 +703,100 @@ fn main() {
                                                SocketType::REP.into(),
                                                SocketType::ROUTER.into(),
                                                SocketType::DEALER.into(),
-                                           ]))
+                                               SocketType::XPUB.into(),
+                                               SocketType::XSUB.into(),
+                                               SocketType::STREAM.into(),
+                                           ])
+            .arg(encoding_arg()))
+        .subcommand(SubCommand::with_name("profiles")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("list"))
+            .subcommand(SubCommand::with_name("show")
+                .arg(Arg::with_name("name").required(true)))
+            .subcommand(set_common_socket_args(SubCommand::with_name("add"),
+                                               &[
+                                                   SocketType::PAIR.into(),
+                                                   SocketType::SUB.into(),
+                                                   SocketType::PUB.into(),
+                                                   SocketType::PULL.into(),
+                                                   SocketType::PUSH.into(),
+                                                   SocketType::REQ.into(),
+                                                   SocketType::REP.into(),
+                                                   SocketType::ROUTER.into(),
+                                                   SocketType::DEALER.into(),
+                                                   SocketType::XPUB.into(),
+                                                   SocketType::XSUB.into(),
+                                                   SocketType::STREAM.into(),
+                                               ])
+                .arg(Arg::with_name("name").required(true))
+                .arg(Arg::with_name("topic")
+                    .long("topic")
+                    .short("t")
+                    .takes_value(true)))
+            .subcommand(SubCommand::with_name("remove")
+                .arg(Arg::with_name("name").required(true))))
+        .subcommand(SubCommand::with_name("proxy")
+            .about("Forwards messages between two sockets, e.g. ROUTER/DEALER, XSUB/XPUB or PULL/PUSH")
+            .arg(Arg::with_name("frontend")
+                .long("frontend")
+                .takes_value(true)
+                .required(true)
+                .validator(validate_socket_spec)
+                .help("TYPE[,bind|connect],ADDRESS or a JSON socket configuration"))
+            .arg(Arg::with_name("backend")
+                .long("backend")
+                .takes_value(true)
+                .required(true)
+                .validator(validate_socket_spec)
+                .help("TYPE[,bind|connect],ADDRESS or a JSON socket configuration"))
+            .arg(Arg::with_name("capture")
+                .long("capture")
+                .takes_value(true)
+                .validator(validation::validate_socket)
+                .help("Bind a socket publishing a copy of every forwarded message"))
+            .arg(Arg::with_name("capture type")
+                .long("capture-type")
+                .possible_values(&[SocketType::PUB.into(), SocketType::PUSH.into()])
+                .default_value("PUB"))
+            .arg(Arg::with_name("capture file")
+                .long("capture-file")
+                .takes_value(true)
+                .help("Append every forwarded message with its direction and timestamp to a file"))
+            .arg(Arg::with_name("control")
+                .long("control")
+                .takes_value(true)
+                .validator(validation::validate_socket)
+                .help("Bind a REP socket taking PAUSE, RESUME, TERMINATE and STATISTICS commands"))
+            .arg(monitor_arg()))
+        .subcommand(SubCommand::with_name("proxy-ctl")
+            .about("Sends a command to the control socket of a proxy")
+            .arg(Arg::with_name("address")
+                .long("address")
+                .short("a")
+                .takes_value(true)
+                .required(true)
+                .validator(validation::validate_socket))
+            .arg(Arg::with_name("command")
+                .required(true)
+                .possible_values(proxy::COMMANDS)
+                .case_insensitive(true))
+            .arg(Arg::with_name("timeout")
+                .long("timeout")
+                .takes_value(true)
+                .default_value("5s")
+                .validator(validation::validate_duration)
+                .help("Fail when the proxy does not answer within this time"))
+            .arg(monitor_arg()))
+        .subcommand(SubCommand::with_name("keygen")
+            .about("Generates a CURVE keypair")
+            .arg(Arg::with_name("path").help("Writes <path> and <path>_secret certificates instead of printing keys")))
         .get_matches();
 
-    match match matches.subcommand() {
-        ("send", Some(matches)) => {
-            let parameters = extract_common_parameters(matches);
-            let message = matches.values_of("message").unwrap().collect::<Vec<_>>().join(" ");
-            send(parameters, &message)
-        }
-        ("listen", Some(matches)) => {
-            let parameters = extract_common_parameters(matches);
-            listen(parameters)
-        }
-        ("chat", Some(matches)) => {
-            let parameters = extract_common_parameters(matches);
-            chat::chat(parameters)
-        }
-        _ => Ok(())
-    } {
-        Ok(()) => {}
-        Err(e) => {
-            println!("Error: {}", e);
-        }
-    };
-}
-
+    let result = run(&matches);
+    monitor::join_printers();
 
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(error::exit_code(e.as_ref()));
+    }
+}
@@ -0,0 +1,183 @@
+use std::error::Error;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::{self, JoinHandle};
+use crate::output::timestamp;
+
+static MONITORS: AtomicUsize = AtomicUsize::new(0);
+static PRINTERS: Mutex<Vec<JoinHandle<()>>> = Mutex::new(Vec::new());
+
+const CONNECTED: u16 = 0x0001;
+const CONNECT_DELAYED: u16 = 0x0002;
+const CONNECT_RETRIED: u16 = 0x0004;
+const LISTENING: u16 = 0x0008;
+const BIND_FAILED: u16 = 0x0010;
+const ACCEPTED: u16 = 0x0020;
+const ACCEPT_FAILED: u16 = 0x0040;
+const CLOSED: u16 = 0x0080;
+const CLOSE_FAILED: u16 = 0x0100;
+const DISCONNECTED: u16 = 0x0200;
+const MONITOR_STOPPED: u16 = 0x0400;
+const HANDSHAKE_FAILED_NO_DETAIL: u16 = 0x0800;
+const HANDSHAKE_SUCCEEDED: u16 = 0x1000;
+const HANDSHAKE_FAILED_PROTOCOL: u16 = 0x2000;
+const HANDSHAKE_FAILED_AUTH: u16 = 0x4000;
+
+const EVENT_NAMES: &[(u16, &str)] = &[
+    (CONNECTED, "CONNECTED"),
+    (CONNECT_DELAYED, "CONNECT_DELAYED"),
+    (CONNECT_RETRIED, "CONNECT_RETRIED"),
+    (LISTENING, "LISTENING"),
+    (BIND_FAILED, "BIND_FAILED"),
+    (ACCEPTED, "ACCEPTED"),
+    (ACCEPT_FAILED, "ACCEPT_FAILED"),
+    (CLOSED, "CLOSED"),
+    (CLOSE_FAILED, "CLOSE_FAILED"),
+    (DISCONNECTED, "DISCONNECTED"),
+    (MONITOR_STOPPED, "MONITOR_STOPPED"),
+    (HANDSHAKE_FAILED_NO_DETAIL, "HANDSHAKE_FAILED_NO_DETAIL"),
+    (HANDSHAKE_SUCCEEDED, "HANDSHAKE_SUCCEEDED"),
+    (HANDSHAKE_FAILED_PROTOCOL, "HANDSHAKE_FAILED_PROTOCOL"),
+    (HANDSHAKE_FAILED_AUTH, "HANDSHAKE_FAILED_AUTH"),
+];
+
+/// Event reported by `zmq_socket_monitor`
+#[derive(Debug, PartialEq)]
+pub struct MonitorEvent {
+    pub event: u16,
+    pub value: u32,
+    pub endpoint: String,
+}
+
+impl MonitorEvent {
+    /// Parses the two frame monitor message: event id and value, then the endpoint
+    pub fn parse(frames: &[Vec<u8>]) -> Option<Self> {
+        match frames {
+            [header, endpoint] if header.len() == 6 => Some(Self {
+                event: u16::from_le_bytes([header[0], header[1]]),
+                value: u32::from_le_bytes([header[2], header[3], header[4], header[5]]),
+                endpoint: String::from_utf8_lossy(endpoint).to_string(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn is(&self, event: zmq::SocketEvent) -> bool {
+        self.event == event.to_raw()
+    }
+
+    pub fn name(&self) -> Option<&'static str> {
+        EVENT_NAMES.iter().find(|(event, _)| *event == self.event).map(|(_, name)| *name)
+    }
+}
+
+impl std::fmt::Display for MonitorEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name)?,
+            None => write!(f, "UNKNOWN({:#06x})", self.event)?,
+        }
+        if !self.endpoint.is_empty() {
+            write!(f, " {}", self.endpoint)?;
+        }
+
+        // The value means something different for every kind of event
+        match self.event {
+            CONNECT_RETRIED => write!(f, " (retrying in {} ms)", self.value),
+            BIND_FAILED | ACCEPT_FAILED | CLOSE_FAILED | HANDSHAKE_FAILED_NO_DETAIL =>
+                write!(f, " ({})", std::io::Error::from_raw_os_error(self.value as i32)),
+            HANDSHAKE_FAILED_PROTOCOL => write!(f, " (protocol error {:#x})", self.value),
+            HANDSHAKE_FAILED_AUTH => write!(f, " (status {})", self.value),
+            CONNECT_DELAYED | HANDSHAKE_SUCCEEDED | MONITOR_STOPPED => Ok(()),
+            _ => write!(f, " (fd {})", self.value),
+        }
+    }
+}
+
+/// Receiving end of a socket monitor
+pub struct Monitor {
+    socket: zmq::Socket,
+}
+
+impl Monitor {
+    /// Starts monitoring the given events, has to be attached before the socket binds or connects
+    /// for the connection events not to be missed
+    pub fn attach(ctx: &zmq::Context, socket: &zmq::Socket, events: u16) -> Result<Self, Box<dyn Error>> {
+        let endpoint = format!("inproc://rzmq.monitor.{}", MONITORS.fetch_add(1, Ordering::SeqCst));
+        socket.monitor(&endpoint, i32::from(events))?;
+
+        let monitor = ctx.socket(zmq::PAIR)?;
+        monitor.connect(&endpoint)?;
+        Ok(Self { socket: monitor })
+    }
+
+    /// Waits up to `timeout_ms` (-1 for no limit) for the next event
+    pub fn next(&self, timeout_ms: i64) -> Result<Option<MonitorEvent>, Box<dyn Error>> {
+        if self.socket.poll(zmq::POLLIN, timeout_ms)? == 0 {
+            return Ok(None);
+        }
+        let frames = self.socket.recv_multipart(0)?;
+        Ok(MonitorEvent::parse(&frames))
+    }
+
+    /// Prints every event to stderr from a background thread until the socket is closed
+    pub fn print_events(self) {
+        let printer = thread::spawn(move || {
+            while let Ok(Some(event)) = self.next(-1) {
+                print_event(&event);
+                if event.event == MONITOR_STOPPED {
+                    break;
+                }
+            }
+        });
+        PRINTERS.lock().unwrap().push(printer);
+    }
+}
+
+/// Waits for the event printers to finish. They keep the context alive, so without this
+/// the process could exit before the closed sockets send their lingering messages.
+pub fn join_printers() {
+    let printers = std::mem::take(&mut *PRINTERS.lock().unwrap());
+    for printer in printers {
+        let _ = printer.join();
+    }
+}
+
+pub fn print_event(event: &MonitorEvent) {
+    eprintln!("{} {}", timestamp(), event);
+}
+
+/// Prints all events of the socket as they happen
+pub fn watch(ctx: &zmq::Context, socket: &zmq::Socket) -> Result<(), Box<dyn Error>> {
+    Monitor::attach(ctx, socket, zmq::SocketEvent::ALL.to_raw())?.print_events();
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parsing_monitor_events() {
+        let event = MonitorEvent::parse(&[vec![0x00, 0x10, 0x07, 0x00, 0x00, 0x00], b"tcp://127.0.0.1:5559".to_vec()]).unwrap();
+
+        assert!(event.is(zmq::SocketEvent::HANDSHAKE_SUCCEEDED));
+        assert_eq!(7, event.value);
+        assert_eq!("tcp://127.0.0.1:5559", event.endpoint);
+
+        assert_eq!(None, MonitorEvent::parse(&[vec![0x00, 0x10]]));
+    }
+
+    #[test]
+    fn describing_monitor_events() {
+        let event = |event, value| MonitorEvent { event, value, endpoint: "tcp://127.0.0.1:5559".to_string() };
+
+        assert_eq!("ACCEPTED tcp://127.0.0.1:5559 (fd 7)", event(ACCEPTED, 7).to_string());
+        assert_eq!("HANDSHAKE_SUCCEEDED tcp://127.0.0.1:5559", event(HANDSHAKE_SUCCEEDED, 0).to_string());
+        assert_eq!("CONNECT_RETRIED tcp://127.0.0.1:5559 (retrying in 200 ms)", event(CONNECT_RETRIED, 200).to_string());
+        assert_eq!("HANDSHAKE_FAILED_AUTH tcp://127.0.0.1:5559 (status 400)", event(HANDSHAKE_FAILED_AUTH, 400).to_string());
+        assert_eq!("UNKNOWN(0x8000) tcp://127.0.0.1:5559 (fd 1)", event(0x8000, 1).to_string());
+        assert!(event(BIND_FAILED, 98).to_string().starts_with("BIND_FAILED tcp://127.0.0.1:5559 ("));
+        assert_eq!(Some("MONITOR_STOPPED"), event(MONITOR_STOPPED, 0).name());
+    }
+}
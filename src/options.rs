@@ -0,0 +1,180 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use crate::socket::SocketType;
+
+/// Socket options applied in `create_socket` before binding or connecting.
+/// Options left as `None` keep the libzmq defaults.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SocketOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sndhwm: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rcvhwm: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub linger: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rcvtimeo: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sndtimeo: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconnect_ivl: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconnect_ivl_max: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backlog: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maxmsgsize: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub immediate: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflate: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tcp_keepalive: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tcp_keepalive_cnt: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tcp_keepalive_idle: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tcp_keepalive_intvl: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ipv6: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub affinity: Option<u64>,
+}
+
+impl SocketOptions {
+    /// Parses a single option given by name as on the command line, e.g. `reconnect-ivl` and `500`.
+    /// Every option is a number or `true`/`false`.
+    pub fn parse(name: &str, value: &str) -> Result<Self, Box<dyn Error>> {
+        let name = name.to_lowercase().replace('-', "_");
+        let value = serde_json::from_str::<serde_json::Value>(value)
+            .ok()
+            .filter(|value| value.is_number() || value.is_boolean())
+            .ok_or_else(|| format!("Invalid value {:?} for {}, expected a number or true/false", value, name))?;
+        let mut option = serde_json::Map::new();
+        option.insert(name.clone(), value);
+        serde_json::from_value(serde_json::Value::Object(option)).map_err(|e| format!("Invalid option {}: {}", name, e).into())
+    }
+
+    /// Rejects options which have no effect on the given socket type,
+    /// e.g. send options on receive-only sockets
+    pub fn validate(&self, socket_type: &SocketType) -> Result<(), Box<dyn Error>> {
+        let sends = !matches!(socket_type, SocketType::SUB | SocketType::PULL);
+        let receives = !matches!(socket_type, SocketType::PUB | SocketType::PUSH);
+        let conflates = matches!(socket_type,
+            SocketType::PUB | SocketType::SUB | SocketType::PUSH | SocketType::PULL | SocketType::DEALER);
+
+        let unsupported = [
+            ("SNDHWM", self.sndhwm.is_some() && !sends),
+            ("SNDTIMEO", self.sndtimeo.is_some() && !sends),
+            ("RCVHWM", self.rcvhwm.is_some() && !receives),
+            ("RCVTIMEO", self.rcvtimeo.is_some() && !receives),
+            ("CONFLATE", self.conflate == Some(true) && !conflates),
+        ];
+
+        match unsupported.iter().find(|(_, unsupported)| *unsupported) {
+            Some((name, _)) => Err(format!("{} is not supported by {} sockets", name, socket_type).into()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn apply(&self, socket: &zmq::Socket) -> Result<(), Box<dyn Error>> {
+        if let Some(value) = self.sndhwm {
+            socket.set_sndhwm(value)?;
+        }
+        if let Some(value) = self.rcvhwm {
+            socket.set_rcvhwm(value)?;
+        }
+        if let Some(value) = self.linger {
+            socket.set_linger(value)?;
+        }
+        if let Some(value) = self.rcvtimeo {
+            socket.set_rcvtimeo(value)?;
+        }
+        if let Some(value) = self.sndtimeo {
+            socket.set_sndtimeo(value)?;
+        }
+        if let Some(value) = self.reconnect_ivl {
+            socket.set_reconnect_ivl(value)?;
+        }
+        if let Some(value) = self.reconnect_ivl_max {
+            socket.set_reconnect_ivl_max(value)?;
+        }
+        if let Some(value) = self.backlog {
+            socket.set_backlog(value)?;
+        }
+        if let Some(value) = self.maxmsgsize {
+            socket.set_maxmsgsize(value)?;
+        }
+        if let Some(value) = self.immediate {
+            socket.set_immediate(value)?;
+        }
+        if let Some(value) = self.conflate {
+            socket.set_conflate(value)?;
+        }
+        if let Some(value) = self.tcp_keepalive {
+            socket.set_tcp_keepalive(value)?;
+        }
+        if let Some(value) = self.tcp_keepalive_cnt {
+            socket.set_tcp_keepalive_cnt(value)?;
+        }
+        if let Some(value) = self.tcp_keepalive_idle {
+            socket.set_tcp_keepalive_idle(value)?;
+        }
+        if let Some(value) = self.tcp_keepalive_intvl {
+            socket.set_tcp_keepalive_intvl(value)?;
+        }
+        if let Some(value) = self.ipv6 {
+            socket.set_ipv6(value)?;
+        }
+        if let Some(value) = self.affinity {
+            socket.set_affinity(value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parsing_options_from_json() {
+        let options: SocketOptions = serde_json::from_str(r#"{ "sndhwm": 10, "conflate": true }"#).unwrap();
+        assert_eq!(Some(10), options.sndhwm);
+        assert_eq!(Some(true), options.conflate);
+        assert_eq!(None, options.linger);
+
+        assert!(serde_json::from_str::<SocketOptions>(r#"{ "sndhmw": 10 }"#).is_err());
+    }
+
+    #[test]
+    fn parsing_single_options() {
+        let options = SocketOptions::parse("reconnect-ivl", "500").unwrap();
+        assert_eq!(Some(500), options.reconnect_ivl);
+        assert_eq!(Some(true), SocketOptions::parse("CONFLATE", "true").unwrap().conflate);
+
+        assert!(SocketOptions::parse("sndhwm", "many").is_err());
+        assert!(SocketOptions::parse("sndhwm", "true").is_err());
+        assert!(SocketOptions::parse("sndhwm", "null").is_err());
+        assert!(SocketOptions::parse("sndhwm", "[10]").is_err());
+        assert!(SocketOptions::parse("sndhmw", "10").is_err());
+    }
+
+    #[test]
+    fn validating_options_against_socket_type() {
+        let options = SocketOptions { sndhwm: Some(10), ..SocketOptions::default() };
+        assert!(options.validate(&SocketType::PUSH).is_ok());
+        assert_eq!("SNDHWM is not supported by PULL sockets",
+                   options.validate(&SocketType::PULL).err().unwrap().to_string());
+
+        let options = SocketOptions { rcvtimeo: Some(10), ..SocketOptions::default() };
+        assert!(options.validate(&SocketType::SUB).is_ok());
+        assert!(options.validate(&SocketType::PUB).is_err());
+
+        let options = SocketOptions { conflate: Some(true), ..SocketOptions::default() };
+        assert!(options.validate(&SocketType::SUB).is_ok());
+        assert!(options.validate(&SocketType::ROUTER).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use serde_json::json;
+use crate::encoding::Encoding;
+
+/// How received messages are printed
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    /// One `received: ...` line per frame
+    #[default]
+    Text,
+    /// One JSON object per message
+    Jsonl,
+}
+
+impl OutputFormat {
+    pub const VALUES: &'static [&'static str] = &["text", "jsonl"];
+}
+
+impl std::convert::From<&str> for OutputFormat {
+    fn from(s: &str) -> Self {
+        match s {
+            "jsonl" => OutputFormat::Jsonl,
+            _ => OutputFormat::Text,
+        }
+    }
+}
+
+/// Describes messages received on a socket as JSON, numbering them as they come
+pub struct JsonLines {
+    socket_type: String,
+    endpoint: String,
+    encoding: Encoding,
+    sequence: u64,
+}
+
+impl JsonLines {
+    pub fn new(socket_type: &str, endpoint: &str, encoding: Encoding) -> Self {
+        Self {
+            socket_type: socket_type.to_string(),
+            endpoint: endpoint.to_string(),
+            encoding,
+            sequence: 0,
+        }
+    }
+
+    pub fn line(&mut self, frames: &[Vec<u8>]) -> String {
+        self.sequence += 1;
+
+        json!({
+            "timestamp": timestamp(),
+            "socket_type": self.socket_type,
+            "endpoint": self.endpoint,
+            "sequence": self.sequence,
+            "frames": describe_frames(frames, self.encoding),
+        }).to_string()
+    }
+}
+
+/// Describes a message forwarded by the proxy, `direction` tells which socket it came from
+pub fn capture_line(direction: &str, frames: &[Vec<u8>]) -> String {
+    json!({
+        "timestamp": timestamp(),
+        "direction": direction,
+        "frames": describe_frames(frames, Encoding::Auto),
+    }).to_string()
+}
+
+fn describe_frames(frames: &[Vec<u8>], encoding: Encoding) -> Vec<serde_json::Value> {
+    frames
+        .iter()
+        .map(|frame| {
+            let encoding = encoding.resolve(frame);
+            json!({
+                "encoding": encoding.to_string(),
+                "size": frame.len(),
+                "data": encoding.encode(frame),
+            })
+        })
+        .collect()
+}
+
+/// Current UTC time in RFC 3339 format with microseconds
+pub fn timestamp() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn describing_messages_as_json() {
+        let mut lines = JsonLines::new("SUB", "tcp://localhost:5559", Encoding::Auto);
+
+        let first: serde_json::Value = serde_json::from_str(&lines.line(&[b"topic".to_vec(), vec![0xff, 0x00]])).unwrap();
+        assert_eq!(1, first["sequence"]);
+        assert_eq!("SUB", first["socket_type"]);
+        assert_eq!("tcp://localhost:5559", first["endpoint"]);
+        assert_eq!(json!({"encoding": "text", "size": 5, "data": "topic"}), first["frames"][0]);
+        assert_eq!(json!({"encoding": "hex", "size": 2, "data": "ff00"}), first["frames"][1]);
+        assert!(first["timestamp"].as_str().unwrap().ends_with('Z'));
+
+        let second: serde_json::Value = serde_json::from_str(&lines.line(&[])).unwrap();
+        assert_eq!(2, second["sequence"]);
+        assert_eq!(json!([]), second["frames"]);
+    }
+
+    #[test]
+    fn describing_captured_messages() {
+        let line: serde_json::Value = serde_json::from_str(&capture_line("frontend->backend", &[vec![0xff]])).unwrap();
+        assert_eq!("frontend->backend", line["direction"]);
+        assert_eq!(json!([{"encoding": "hex", "size": 1, "data": "ff"}]), line["frames"]);
+    }
+}
@@ -0,0 +1,137 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::socket::SocketParameters;
+
+pub type Profiles<'a> = BTreeMap<Cow<'a, str>, SocketParameters<'a>>;
+
+/// Location of the user level profiles file:
+/// `$XDG_CONFIG_HOME/rzmq/profiles.json`, falling back to `~/.config/rzmq/profiles.json`
+pub fn path() -> Result<PathBuf, Box<dyn Error>> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("rzmq").join("profiles.json"))
+        .ok_or_else(|| "Cannot locate profiles file, neither XDG_CONFIG_HOME nor HOME is set".into())
+}
+
+/// Reads the profiles file, a missing file is treated as an empty one
+pub fn read() -> Result<String, Box<dyn Error>> {
+    let path = path()?;
+    if !path.exists() {
+        return Ok("{}".to_string());
+    }
+    fs::read_to_string(&path).map_err(|e| format!("Cannot read profiles file {}: {}", path.display(), e).into())
+}
+
+pub fn parse(json: &str) -> Result<Profiles<'_>, Box<dyn Error>> {
+    serde_json::from_str(json).map_err(|e| format!("Invalid profiles file: {}", e).into())
+}
+
+pub fn find<'a>(json: &'a str, name: &str) -> Result<SocketParameters<'a>, Box<dyn Error>> {
+    parse(json)?
+        .remove(name)
+        .ok_or_else(|| format!("No profile named {}", name).into())
+}
+
+/// Writes the profiles file readable by the owner only, as it may hold PLAIN passwords and CURVE secret keys
+pub fn save(profiles: &Profiles) -> Result<(), Box<dyn Error>> {
+    let path = path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    write_private(&path, serde_json::to_string_pretty(profiles)?.as_bytes())
+        .map_err(|e| format!("Cannot write profiles file {}: {}", path.display(), e).into())
+}
+
+fn write_private(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    // The mode only applies to a new file, one written by an earlier version may still be readable by others
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(content)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::security::PlainParameters;
+
+    const PROFILES: &str = r#"{
+        "telemetry-sub": {
+            "address": "tcp://localhost:5559",
+            "socket_type": "SUB",
+            "association_type": "connect",
+            "socket_id": null,
+            "topic": "TELEMETRY"
+        },
+        "jobs": {
+            "address": "ipc:///tmp/jobs",
+            "socket_type": "PUSH",
+            "association_type": "connect",
+            "socket_id": null,
+            "topic": null
+        }
+    }"#;
+
+    #[test]
+    fn finding_profile_by_name() {
+        let parameters = find(PROFILES, "telemetry-sub").unwrap();
+        assert_eq!("tcp://localhost:5559", parameters.address);
+        assert_eq!(Some("TELEMETRY"), parameters.topic.as_deref());
+
+        assert_eq!("No profile named missing", find(PROFILES, "missing").err().unwrap().to_string());
+    }
+
+    #[test]
+    fn profiles_survive_round_trip() {
+        let profiles = parse(PROFILES).unwrap();
+        let json = serde_json::to_string(&profiles).unwrap();
+        let parsed = parse(&json).unwrap();
+
+        assert_eq!(vec!["jobs", "telemetry-sub"], parsed.keys().cloned().collect::<Vec<_>>());
+        assert_eq!("ipc:///tmp/jobs", parsed["jobs"].address);
+    }
+
+    #[test]
+    fn escaped_values_survive_round_trip() {
+        let mut profiles = Profiles::new();
+        profiles.insert("quoted \"name\"".into(), SocketParameters {
+            address: "ipc://C:\\tmp\\jobs".into(),
+            plain: PlainParameters { password: Some("pass\"word\\".into()), ..Default::default() },
+            ..Default::default()
+        });
+        let json = serde_json::to_string(&profiles).unwrap();
+        let parsed = parse(&json).unwrap();
+
+        let parameters = &parsed["quoted \"name\""];
+        assert_eq!("ipc://C:\\tmp\\jobs", parameters.address);
+        assert_eq!(Some("pass\"word\\"), parameters.plain.password.as_deref());
+    }
+
+    #[test]
+    fn saved_profiles_are_private() {
+        let path = std::env::temp_dir().join(format!("rzmq-profiles-{}.json", std::process::id()));
+        write_private(&path, b"{}").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(0o600, fs::metadata(&path).unwrap().permissions().mode() & 0o777);
+        }
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,95 @@
+use std::convert::TryInto;
+
+/// Commands understood by the control socket of a steerable proxy
+pub const COMMANDS: &[&str] = &["PAUSE", "RESUME", "TERMINATE", "STATISTICS"];
+
+/// Traffic through one side of the proxy, a multipart message counts once
+#[derive(Debug, Default, PartialEq)]
+pub struct Counters {
+    pub messages_in: u64,
+    pub bytes_in: u64,
+    pub messages_out: u64,
+    pub bytes_out: u64,
+}
+
+/// Proxy counters as replied to STATISTICS, eight frames holding a native endian u64 each
+#[derive(Debug, Default, PartialEq)]
+pub struct Statistics {
+    pub frontend: Counters,
+    pub backend: Counters,
+}
+
+impl Statistics {
+    pub fn decode(frames: &[Vec<u8>]) -> Option<Self> {
+        let values = frames
+            .iter()
+            .map(|frame| frame.as_slice().try_into().ok().map(u64::from_ne_bytes))
+            .collect::<Option<Vec<_>>>()?;
+
+        match values.as_slice() {
+            [a, b, c, d, e, f, g, h] => Some(Self {
+                frontend: Counters { messages_in: *a, bytes_in: *b, messages_out: *c, bytes_out: *d },
+                backend: Counters { messages_in: *e, bytes_in: *f, messages_out: *g, bytes_out: *h },
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<Vec<u8>> {
+        [&self.frontend, &self.backend]
+            .iter()
+            .flat_map(|c| vec![c.messages_in, c.bytes_in, c.messages_out, c.bytes_out])
+            .map(|value| value.to_ne_bytes().to_vec())
+            .collect()
+    }
+
+    /// Counts a message of `size` bytes forwarded from the frontend to the backend or the other way
+    pub fn count(&mut self, from_frontend: bool, size: usize) {
+        let (from, to) = if from_frontend {
+            (&mut self.frontend, &mut self.backend)
+        } else {
+            (&mut self.backend, &mut self.frontend)
+        };
+        from.messages_in += 1;
+        from.bytes_in += size as u64;
+        to.messages_out += 1;
+        to.bytes_out += size as u64;
+    }
+}
+
+impl std::fmt::Display for Counters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "received {} messages ({} bytes), sent {} messages ({} bytes)",
+               self.messages_in, self.bytes_in, self.messages_out, self.bytes_out)
+    }
+}
+
+impl std::fmt::Display for Statistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "frontend: {}\nbackend: {}", self.frontend, self.backend)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn counting_and_encoding_statistics() {
+        let mut statistics = Statistics::default();
+        statistics.count(true, 5);
+        statistics.count(true, 3);
+        statistics.count(false, 2);
+
+        assert_eq!(Counters { messages_in: 2, bytes_in: 8, messages_out: 1, bytes_out: 2 }, statistics.frontend);
+        assert_eq!(Counters { messages_in: 1, bytes_in: 2, messages_out: 2, bytes_out: 8 }, statistics.backend);
+        let encoded = statistics.encode();
+        assert_eq!(8, encoded.len());
+        assert_eq!(Some(statistics), Statistics::decode(&encoded));
+
+        assert_eq!("frontend: received 0 messages (0 bytes), sent 0 messages (0 bytes)\n\
+                    backend: received 0 messages (0 bytes), sent 0 messages (0 bytes)", Statistics::default().to_string());
+        assert_eq!(None, Statistics::decode(&[vec![0; 8]]));
+        assert_eq!(None, Statistics::decode(&vec![vec![0; 4]; 8]));
+    }
+}
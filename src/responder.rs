@@ -0,0 +1,106 @@
+use std::error::Error;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// How `serve` answers requests
+pub enum Responder {
+    /// Send the request back
+    Echo,
+    /// Always the same reply, e.g. read from a file
+    Fixed(Vec<u8>),
+    /// Reply with `{body}` replaced by the request body and `{0}`, `{1}`... by its frames
+    Template(String),
+    /// Shell command getting the request body on stdin, its output is the reply
+    Exec(String),
+}
+
+impl Responder {
+    pub fn respond(&self, request: &[Vec<u8>]) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+        match self {
+            Responder::Echo => Ok(request.to_vec()),
+            Responder::Fixed(reply) => Ok(vec![reply.clone()]),
+            Responder::Template(template) => Ok(vec![render(template, request).into_bytes()]),
+            Responder::Exec(command) => Ok(vec![execute(command, request.concat())?]),
+        }
+    }
+}
+
+fn render(template: &str, request: &[Vec<u8>]) -> String {
+    regex::Regex::new(r"\{(body|\d+)\}")
+        .unwrap()
+        .replace_all(template, |captures: &regex::Captures| {
+            let value = match &captures[1] {
+                "body" => Some(request.concat()),
+                index => index.parse::<usize>().ok().and_then(|i| request.get(i)).cloned(),
+            };
+            match value {
+                Some(value) => String::from_utf8_lossy(&value).to_string(),
+                None => captures[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+fn execute(command: &str, input: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Cannot run {:?}: {}", command, e))?;
+
+    // Written from another thread so a command producing output before reading all of its input cannot block
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = thread::spawn(move || stdin.write_all(&input));
+
+    let output = child.wait_with_output()?;
+    let _ = writer.join();
+    if !output.status.success() {
+        eprintln!("{:?} exited with {}", command, output.status);
+    }
+    Ok(output.stdout)
+}
+
+/// Splits a message received on a ROUTER into its envelope and the request. The envelope holds
+/// the routing ids up to the empty delimiter frame sent by REQ peers, or just the peer id without it.
+pub fn split_envelope(mut frames: Vec<Vec<u8>>) -> (Vec<Vec<u8>>, Vec<Vec<u8>>) {
+    let envelope_size = match frames.iter().position(Vec::is_empty) {
+        Some(delimiter) => delimiter + 1,
+        None => frames.len().min(1),
+    };
+    let request = frames.split_off(envelope_size);
+    (frames, request)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rendering_templates() {
+        let request = [b"Hello".to_vec(), b"World".to_vec()];
+
+        assert_eq!("body=HelloWorld first=Hello second=World {2} {other}",
+                   render("body={body} first={0} second={1} {2} {other}", &request));
+    }
+
+    #[test]
+    fn responding() {
+        let request = [b"ping".to_vec()];
+
+        assert_eq!(request.to_vec(), Responder::Echo.respond(&request).unwrap());
+        assert_eq!(vec![b"pong".to_vec()], Responder::Fixed(b"pong".to_vec()).respond(&request).unwrap());
+        assert_eq!(vec![b"PING".to_vec()], Responder::Exec("tr a-z A-Z".to_string()).respond(&request).unwrap());
+    }
+
+    #[test]
+    fn splitting_envelopes() {
+        let from_req = vec![b"id".to_vec(), vec![], b"request".to_vec()];
+        assert_eq!((vec![b"id".to_vec(), vec![]], vec![b"request".to_vec()]), split_envelope(from_req));
+
+        let from_dealer = vec![b"id".to_vec(), b"request".to_vec()];
+        assert_eq!((vec![b"id".to_vec()], vec![b"request".to_vec()]), split_envelope(from_dealer));
+    }
+}
@@ -0,0 +1,173 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs;
+use serde::{Deserialize, Serialize};
+
+/// CURVE settings. Keys are given either in Z85 or as a path to a czmq certificate file.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CurveParameters<'a> {
+    pub server: bool,
+    #[serde(borrow, skip_serializing_if = "Option::is_none")]
+    pub secret_key: Option<Cow<'a, str>>,
+    #[serde(borrow, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<Cow<'a, str>>,
+    #[serde(borrow, skip_serializing_if = "Option::is_none")]
+    pub server_key: Option<Cow<'a, str>>,
+}
+
+impl CurveParameters<'_> {
+    pub fn is_enabled(&self) -> bool {
+        self.server || self.secret_key.is_some() || self.public_key.is_some() || self.server_key.is_some()
+    }
+
+    /// Configures the socket as a CURVE server or client. A client without its own
+    /// keys gets a freshly generated keypair, so only the server key is mandatory.
+    pub fn apply(&self, socket: &zmq::Socket) -> Result<(), Box<dyn Error>> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        if zmq::has("curve") != Some(true) {
+            return Err("libzmq was built without CURVE support".into());
+        }
+
+        if self.server {
+            let secret_key = self.secret_key.as_deref().ok_or("CURVE server requires --curve-secret-key")?;
+            socket.set_curve_server(true)?;
+            socket.set_curve_secretkey(&load_key(secret_key, Key::Secret)?)?;
+            return Ok(());
+        }
+
+        let server_key = self.server_key.as_deref().ok_or("CURVE client requires --curve-server-key")?;
+        let (public_key, secret_key) = match (self.public_key.as_deref(), self.secret_key.as_deref()) {
+            (Some(public_key), Some(secret_key)) => (load_key(public_key, Key::Public)?, load_key(secret_key, Key::Secret)?),
+            (None, Some(secret_key)) if !is_z85_key(secret_key) => (load_key(secret_key, Key::Public)?, load_key(secret_key, Key::Secret)?),
+            (None, Some(_)) => return Err("CURVE client requires --curve-public-key along with a Z85 --curve-secret-key".into()),
+            (Some(_), None) => return Err("CURVE client requires --curve-secret-key along with --curve-public-key".into()),
+            (None, None) => {
+                let keypair = zmq::CurveKeyPair::new()?;
+                (keypair.public_key.to_vec(), keypair.secret_key.to_vec())
+            }
+        };
+
+        socket.set_curve_serverkey(&load_key(server_key, Key::Public)?)?;
+        socket.set_curve_publickey(&public_key)?;
+        socket.set_curve_secretkey(&secret_key)?;
+        Ok(())
+    }
+}
+
+/// PLAIN settings, a server checks credentials with the ZAP handler (if running)
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlainParameters<'a> {
+    pub server: bool,
+    #[serde(borrow, skip_serializing_if = "Option::is_none")]
+    pub username: Option<Cow<'a, str>>,
+    #[serde(borrow, skip_serializing_if = "Option::is_none")]
+    pub password: Option<Cow<'a, str>>,
+}
+
+impl PlainParameters<'_> {
+    pub fn apply(&self, socket: &zmq::Socket) -> Result<(), Box<dyn Error>> {
+        if self.server {
+            socket.set_plain_server(true)?;
+        } else if self.username.is_some() || self.password.is_some() {
+            socket.set_plain_username(Some(self.username.as_deref().unwrap_or("")))?;
+            socket.set_plain_password(Some(self.password.as_deref().unwrap_or("")))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Key {
+    Public,
+    Secret,
+}
+
+impl Key {
+    fn certificate_field(self) -> &'static str {
+        match self {
+            Key::Public => "public-key",
+            Key::Secret => "secret-key",
+        }
+    }
+}
+
+fn is_z85_key(value: &str) -> bool {
+    value.len() == 40 && zmq::z85_decode(value).is_ok()
+}
+
+/// Decodes a Z85 key, or reads it from a czmq certificate file when the value is not a key
+pub fn load_key(value: &str, key: Key) -> Result<Vec<u8>, Box<dyn Error>> {
+    if is_z85_key(value) {
+        return Ok(zmq::z85_decode(value)?);
+    }
+
+    let certificate = fs::read_to_string(value)
+        .map_err(|e| format!("{} is neither a Z85 key nor a readable certificate: {}", value, e))?;
+    let encoded = certificate_key(&certificate, key)
+        .ok_or_else(|| format!("Certificate {} has no {}", value, key.certificate_field()))?;
+    zmq::z85_decode(&encoded).map_err(|e| format!("Invalid {} in {}: {}", key.certificate_field(), value, e).into())
+}
+
+pub fn certificate_key(certificate: &str, key: Key) -> Option<String> {
+    let pattern = format!(r#"(?m)^\s*{}\s*=\s*"(.{{40}})"\s*$"#, key.certificate_field());
+    regex::Regex::new(&pattern)
+        .unwrap()
+        .captures(certificate)
+        .map(|c| c[1].to_string())
+}
+
+/// Formats a keypair as czmq certificates, returning the public and the secret certificate
+pub fn certificates(keypair: &zmq::CurveKeyPair) -> Result<(String, String), Box<dyn Error>> {
+    let public_key = zmq::z85_encode(&keypair.public_key)?;
+    let secret_key = zmq::z85_encode(&keypair.secret_key)?;
+
+    let public = format!(
+        "#   ****  Generated by rzmq  ****\n\
+         #   ZeroMQ CURVE Public Certificate\n\
+         #   Exchange securely, or use a secure mechanism to verify the contents\n\
+         #   of this file after exchange. Store public certificates in your home\n\
+         #   directory, in the .curve subdirectory.\n\
+         \n\
+         metadata\n\
+         curve\n    public-key = \"{}\"\n", public_key);
+    let secret = format!(
+        "#   ****  Generated by rzmq  ****\n\
+         #   ZeroMQ CURVE **Secret** Certificate\n\
+         #   DO NOT PROVIDE THIS FILE TO OTHER USERS nor change its permissions.\n\
+         \n\
+         metadata\n\
+         curve\n    public-key = \"{}\"\n    secret-key = \"{}\"\n", public_key, secret_key);
+
+    Ok((public, secret))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reading_keys_from_certificates() {
+        let keypair = zmq::CurveKeyPair::new().unwrap();
+        let (public, secret) = certificates(&keypair).unwrap();
+
+        let public_key = zmq::z85_encode(&keypair.public_key).unwrap();
+        let secret_key = zmq::z85_encode(&keypair.secret_key).unwrap();
+
+        assert_eq!(Some(public_key.clone()), certificate_key(&public, Key::Public));
+        assert_eq!(None, certificate_key(&public, Key::Secret));
+        assert_eq!(Some(public_key), certificate_key(&secret, Key::Public));
+        assert_eq!(Some(secret_key), certificate_key(&secret, Key::Secret));
+    }
+
+    #[test]
+    fn loading_keys() {
+        let key = "rq:rM>}U?@Lns47E1%kR.o@n%FcmmsL/@{H8]yf7";
+        assert_eq!(32, load_key(key, Key::Public).unwrap().len());
+        assert!(load_key("not/a/key/file", Key::Public).is_err());
+    }
+}
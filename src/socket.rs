@@ -1,31 +1,75 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::error::Error;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use crate::encoding::Encoding;
+use crate::monitor;
+use crate::options::SocketOptions;
+use crate::security::{CurveParameters, PlainParameters};
+use crate::validation;
+use crate::zap::{self, ZapParameters, ZapPolicy};
 
-#[derive(Deserialize)]
+#[derive(Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
 pub enum AssociationType {
+    #[default]
     #[serde(alias = "bind")]
     Bind,
     #[serde(alias = "connect")]
     Connect,
 }
 
-impl Default for AssociationType {
-    fn default() -> Self {
-        AssociationType::Bind
-    }
-}
-
-#[derive(Default, Deserialize)]
+#[derive(Default, Deserialize, Serialize)]
 pub struct SocketParameters<'a>
 {
-    pub address: &'a str,
+    /// Main endpoint, may be left out when `bind` or `connect` are given
+    #[serde(default, borrow)]
+    pub address: Cow<'a, str>,
     pub socket_type: SocketType,
+    #[serde(default)]
     pub association_type: AssociationType,
-    pub socket_id: Option<&'a str>,
-    pub topic: Option<&'a str>,
+    /// Further endpoints to bind
+    #[serde(default, borrow, skip_serializing_if = "Vec::is_empty")]
+    pub bind: Vec<Cow<'a, str>>,
+    /// Further endpoints to connect to
+    #[serde(default, borrow, skip_serializing_if = "Vec::is_empty")]
+    pub connect: Vec<Cow<'a, str>>,
+    #[serde(borrow)]
+    pub socket_id: Option<Cow<'a, str>>,
+    #[serde(borrow)]
+    pub topic: Option<Cow<'a, str>>,
+    #[serde(default)]
+    pub options: SocketOptions,
+    #[serde(default, borrow)]
+    pub curve: CurveParameters<'a>,
+    #[serde(default, borrow)]
+    pub plain: PlainParameters<'a>,
+    #[serde(default, borrow)]
+    pub zap: ZapParameters<'a>,
+    /// Print the socket events as they happen
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub monitor: bool,
+    /// Where to write the endpoints actually bound, one per line
+    #[serde(borrow)]
+    pub endpoint_file: Option<Cow<'a, str>>,
+}
+
+impl<'a> SocketParameters<'a> {
+    /// Every endpoint with how it is associated: the main address first, then `bind` and `connect`
+    pub fn endpoints(&self) -> Vec<(AssociationType, &str)> {
+        Some((self.association_type, self.address.as_ref()))
+            .filter(|(_, address)| !address.is_empty())
+            .into_iter()
+            .chain(self.bind.iter().map(|address| (AssociationType::Bind, address.as_ref())))
+            .chain(self.connect.iter().map(|address| (AssociationType::Connect, address.as_ref())))
+            .collect()
+    }
+
+    pub fn addresses(&self) -> Vec<&str> {
+        self.endpoints().into_iter().map(|(_, address)| address).collect()
+    }
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
 #[allow(non_camel_case_types)]
 pub enum SocketType {
     PUB,
@@ -34,18 +78,18 @@ pub enum SocketType {
     REP,
     PUSH,
     PULL,
+    #[default]
     PAIR,
     ROUTER,
     DEALER,
-}
-
-impl Default for SocketType {
-    fn default() -> Self {
-        SocketType::PAIR
-    }
+    XPUB,
+    XSUB,
+    STREAM,
 }
 
 impl SocketType {
+    pub const VALUES: &'static [&'static str] = &["PUB", "SUB", "REQ", "REP", "PUSH", "PULL", "PAIR", "ROUTER", "DEALER", "XPUB", "XSUB", "STREAM"];
+
     pub fn default_association(&self) -> AssociationType {
         match self {
             Self::PUB => AssociationType::Bind,
@@ -57,6 +101,9 @@ impl SocketType {
             Self::PAIR => AssociationType::Bind,
             Self::ROUTER => AssociationType::Bind,
             Self::DEALER => AssociationType::Bind,
+            Self::XPUB => AssociationType::Bind,
+            Self::XSUB => AssociationType::Connect,
+            Self::STREAM => AssociationType::Connect,
         }
     }
 }
@@ -79,6 +126,9 @@ impl std::convert::From<&SocketType> for &str {
             SocketType::PAIR => "PAIR",
             SocketType::ROUTER => "ROUTER",
             SocketType::DEALER => "DEALER",
+            SocketType::XPUB => "XPUB",
+            SocketType::XSUB => "XSUB",
+            SocketType::STREAM => "STREAM",
         }
     }
 }
@@ -95,6 +145,9 @@ impl std::convert::From<&str> for SocketType {
             "PAIR" => SocketType::PAIR,
             "ROUTER" => SocketType::ROUTER,
             "DEALER" => SocketType::DEALER,
+            "XPUB" => SocketType::XPUB,
+            "XSUB" => SocketType::XSUB,
+            "STREAM" => SocketType::STREAM,
             _ => SocketType::PAIR,
         }
     }
@@ -107,7 +160,17 @@ impl std::fmt::Display for SocketType {
 }
 
 pub fn create_socket(ctx: &zmq::Context, parameters: &SocketParameters) -> Result<zmq::Socket, Box<dyn Error>> {
-    println!("Socket type: {}", parameters.socket_type);
+    let socket = configure_socket(ctx, parameters)?;
+    if parameters.monitor {
+        monitor::watch(ctx, &socket)?;
+    }
+    associate(&socket, parameters)?;
+    Ok(socket)
+}
+
+/// Creates the socket with all its options set, but neither bound nor connected yet
+pub fn configure_socket(ctx: &zmq::Context, parameters: &SocketParameters) -> Result<zmq::Socket, Box<dyn Error>> {
+    eprintln!("Socket type: {}", parameters.socket_type);
 
     let socket = ctx.socket(match parameters.socket_type {
         SocketType::PUB => zmq::PUB,
@@ -119,24 +182,225 @@ pub fn create_socket(ctx: &zmq::Context, parameters: &SocketParameters) -> Resul
         SocketType::REP => zmq::REP,
         SocketType::ROUTER => zmq::ROUTER,
         SocketType::DEALER => zmq::DEALER,
+        SocketType::XPUB => zmq::XPUB,
+        SocketType::XSUB => zmq::XSUB,
+        SocketType::STREAM => zmq::STREAM,
     })?;
 
-    if let Some(id) = parameters.socket_id {
+    if let Some(id) = parameters.socket_id.as_deref() {
         socket.set_identity(id.as_bytes())?;
     }
 
-    let _ = socket.set_subscribe(parameters.topic.unwrap_or("").as_bytes());
+    parameters.options.validate(&parameters.socket_type)?;
+    parameters.options.apply(&socket)?;
+    parameters.curve.apply(&socket)?;
+    parameters.plain.apply(&socket)?;
 
-    match parameters.association_type {
-        AssociationType::Connect => socket.connect(parameters.address)?,
-        AssociationType::Bind => socket.bind(parameters.address)?,
-    };
+    if parameters.zap.is_enabled() {
+        let domain = zap::register(ctx, ZapPolicy::load(&parameters.zap)?)?;
+        socket.set_zap_domain(&domain)?;
+    }
+
+    if let SocketType::XPUB = parameters.socket_type {
+        socket.set_xpub_verbose(true)?;
+    }
+
+    let _ = socket.set_subscribe(parameters.topic.as_deref().unwrap_or("").as_bytes());
 
     Ok(socket)
 }
 
-pub fn parse(json: &str) -> SocketParameters {
-    serde_json::from_str(json).unwrap() //.unwrap_or_else(|_| SocketParameters::default())
+/// Binds or connects a socket made by `configure_socket`, after attaching a monitor if needed.
+/// Returns the endpoints as associated, with the port picked for a wildcard bind.
+pub fn associate(socket: &zmq::Socket, parameters: &SocketParameters) -> Result<Vec<(AssociationType, String)>, Box<dyn Error>> {
+    let mut endpoints = Vec::new();
+    let mut bound = Vec::new();
+    for (association_type, address) in parameters.endpoints() {
+        match association_type {
+            AssociationType::Connect => {
+                socket.connect(address)?;
+                endpoints.push((association_type, address.to_string()));
+            }
+            AssociationType::Bind => {
+                socket.bind(address)?;
+                // Differs from the address when binding a wildcard, e.g. tcp://*:*
+                let endpoint = socket.get_last_endpoint()?.map_err(|_| "Bound endpoint is not valid UTF-8")?;
+                eprintln!("Bound to {:?}", endpoint);
+                endpoints.push((association_type, endpoint.clone()));
+                bound.push(endpoint);
+            }
+        };
+    }
+
+    if let Some(path) = parameters.endpoint_file.as_deref() {
+        write_endpoints(path, &bound).map_err(|e| format!("Cannot write endpoint file {}: {}", path, e))?;
+    }
+
+    // Without a topic an XSUB subscribes later, through its own subscription messages
+    if let (SocketType::XSUB, Some(topic)) = (&parameters.socket_type, parameters.topic.as_deref()) {
+        socket.send(Subscription::Subscribe(topic.as_bytes()).encode(), 0)?;
+    }
+
+    Ok(endpoints)
+}
+
+/// Undoes a bind, missing from the zmq crate. The endpoint has to be given as bound, e.g. with the port
+/// picked for a wildcard.
+pub fn unbind(socket: &mut zmq::Socket, endpoint: &str) -> Result<(), Box<dyn Error>> {
+    let endpoint = std::ffi::CString::new(endpoint)?;
+    // The socket pointer stays valid as long as the borrowed socket, the endpoint is copied by libzmq
+    if unsafe { zmq_sys::zmq_unbind(socket.as_mut_ptr(), endpoint.as_ptr()) } == -1 {
+        return Err(zmq::Error::from_raw(unsafe { zmq_sys::zmq_errno() }).into());
+    }
+    Ok(())
+}
+
+/// Written aside and renamed so that whoever waits for the file never reads it half written
+fn write_endpoints(path: &str, endpoints: &[String]) -> std::io::Result<()> {
+    let temporary = format!("{}.tmp", path);
+    std::fs::write(&temporary, endpoints.iter().map(|endpoint| format!("{}\n", endpoint)).collect::<String>())?;
+    std::fs::rename(temporary, path)
+}
+
+/// Subscription message as sent upstream by SUB and XSUB sockets,
+/// a single frame prefixed with 0x01 (subscribe) or 0x00 (unsubscribe)
+#[derive(Debug, PartialEq)]
+pub enum Subscription<'a> {
+    Subscribe(&'a [u8]),
+    Unsubscribe(&'a [u8]),
+}
+
+impl<'a> Subscription<'a> {
+    pub fn decode(frame: &'a [u8]) -> Option<Self> {
+        match frame.split_first() {
+            Some((1, topic)) => Some(Subscription::Subscribe(topic)),
+            Some((0, topic)) => Some(Subscription::Unsubscribe(topic)),
+            _ => None,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let (prefix, topic) = match self {
+            Subscription::Subscribe(topic) => (1, topic),
+            Subscription::Unsubscribe(topic) => (0, topic),
+        };
+        let mut frame = vec![prefix];
+        frame.extend_from_slice(topic);
+        frame
+    }
+}
+
+impl std::fmt::Display for Subscription<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Subscription::Subscribe(topic) => write!(f, "subscribe: {:?}", String::from_utf8_lossy(topic)),
+            Subscription::Unsubscribe(topic) => write!(f, "unsubscribe: {:?}", String::from_utf8_lossy(topic)),
+        }
+    }
+}
+
+pub fn parse(json: &str) -> Result<SocketParameters<'_>, Box<dyn Error>> {
+    serde_json::from_str(json).map_err(|e| format!("Invalid socket configuration: {}", e).into())
+}
+
+/// Parses a socket given on the command line, either as `TYPE[,bind|connect],ADDRESS`
+/// (associated the default way for the type unless told) or as a JSON configuration
+pub fn parse_spec(spec: &str) -> Result<SocketParameters<'_>, Box<dyn Error>> {
+    if spec.trim_start().starts_with('{') {
+        return parse(spec);
+    }
+
+    let parts = spec.splitn(3, ',').collect::<Vec<_>>();
+    let (socket_type, association, address) = match parts.as_slice() {
+        [socket_type, address] => (*socket_type, None, *address),
+        [socket_type, association, address] => (*socket_type, Some(*association), *address),
+        _ => return Err(format!("Invalid socket {:?}, expected TYPE[,bind|connect],ADDRESS", spec).into()),
+    };
+
+    if !SocketType::VALUES.contains(&socket_type) {
+        return Err(format!("Invalid socket {:?}, unknown type {}", spec, socket_type).into());
+    }
+    let socket_type = SocketType::from(socket_type);
+
+    let association_type = match association {
+        Some("bind") => AssociationType::Bind,
+        Some("connect") => AssociationType::Connect,
+        Some(other) => return Err(format!("Invalid socket {:?}, expected bind or connect instead of {}", spec, other).into()),
+        None => socket_type.default_association(),
+    };
+    validation::validate_socket(address.to_string()).map_err(|e| format!("Invalid socket {:?}: {}", spec, e))?;
+
+    Ok(SocketParameters {
+        address: address.into(),
+        socket_type,
+        association_type,
+        ..SocketParameters::default()
+    })
+}
+
+/// Event decoded from a `[routing id, data]` message received by a STREAM socket
+#[derive(Debug, PartialEq)]
+pub enum StreamEvent<'a> {
+    Connected(&'a [u8]),
+    Disconnected(&'a [u8]),
+    Data(&'a [u8], &'a [u8]),
+}
+
+impl StreamEvent<'_> {
+    pub fn describe(&self, encoding: Encoding) -> String {
+        match self {
+            StreamEvent::Connected(id) => format!("connected: {}", format_peer_id(id)),
+            StreamEvent::Disconnected(id) => format!("disconnected: {}", format_peer_id(id)),
+            StreamEvent::Data(id, data) => format!("received from {}: {}", format_peer_id(id), encoding.display(data)),
+        }
+    }
+}
+
+impl std::fmt::Display for StreamEvent<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.describe(Encoding::Text))
+    }
+}
+
+/// Tracks peers of a STREAM socket. An empty data frame from an unknown peer
+/// means it has connected, from a known one that it has disconnected.
+#[derive(Default)]
+pub struct StreamPeers {
+    peers: HashSet<Vec<u8>>,
+}
+
+impl StreamPeers {
+    pub fn event<'a>(&mut self, frames: &'a [Vec<u8>]) -> Option<StreamEvent<'a>> {
+        match frames {
+            [id, data] if data.is_empty() => {
+                if self.peers.remove(id) {
+                    Some(StreamEvent::Disconnected(id))
+                } else {
+                    self.peers.insert(id.clone());
+                    Some(StreamEvent::Connected(id))
+                }
+            }
+            [id, data] => Some(StreamEvent::Data(id, data)),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, id: &[u8]) -> bool {
+        self.peers.contains(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Vec<u8>> {
+        self.peers.iter()
+    }
+}
+
+/// STREAM routing ids are binary, so they are shown and typed in as hex
+pub fn format_peer_id(id: &[u8]) -> String {
+    Encoding::Hex.encode(id)
+}
+
+pub fn parse_peer_id(id: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    Encoding::Hex.decode(id).map_err(|_| format!("Invalid peer id {}, expected hex digits", id).into())
 }
 
 #[cfg(test)]
@@ -154,8 +418,115 @@ mod test {
                 "topic": "TOPIC1"
             }"#;
 
-        let parsed = parse(json);
-        assert_eq!(Some("TOPIC1"), parsed.topic);
+        let parsed = parse(json).unwrap();
+        assert_eq!(Some("TOPIC1"), parsed.topic.as_deref());
+    }
+
+    #[test]
+    fn parsing_multiple_endpoints() {
+        let json = r#"{
+            "socket_type": "SUB",
+            "connect": ["tcp://a:5559", "tcp://b:5559"],
+            "bind": ["ipc:///tmp/x"]
+        }"#;
+
+        let parsed = parse(json).unwrap();
+        assert_eq!(vec!["ipc:///tmp/x", "tcp://a:5559", "tcp://b:5559"], parsed.addresses());
+        assert!(matches!(parsed.endpoints()[1], (AssociationType::Connect, "tcp://a:5559")));
+
+        let parsed = parse(r#"{"address": "tcp://*:5559", "socket_type": "PUB", "association_type": "bind", "connect": ["tcp://c:1"]}"#).unwrap();
+        assert_eq!(vec!["tcp://*:5559", "tcp://c:1"], parsed.addresses());
+    }
+
+    #[test]
+    fn parsing_socket_specs() {
+        let parsed = parse_spec("ROUTER,tcp://*:5559").unwrap();
+        assert_eq!("tcp://*:5559", parsed.address);
+        assert!(matches!(parsed.socket_type, SocketType::ROUTER));
+        assert!(matches!(parsed.association_type, AssociationType::Bind));
+
+        let parsed = parse_spec("DEALER,connect,tcp://localhost:5560").unwrap();
+        assert!(matches!(parsed.association_type, AssociationType::Connect));
+
+        let parsed = parse_spec(r#"{"address": "ipc://backend", "socket_type": "XPUB", "association_type": "bind"}"#).unwrap();
+        assert!(matches!(parsed.socket_type, SocketType::XPUB));
+
+        assert!(parse_spec("tcp://localhost:5559").is_err());
+        assert!(parse_spec("QUEUE,tcp://localhost:5559").is_err());
+        assert!(parse_spec("PUSH,listen,tcp://localhost:5559").is_err());
+        assert!(parse_spec("PUSH,localhost:5559").is_err());
+    }
+
+    #[test]
+    fn subscription_frames() {
+        assert_eq!(Some(Subscription::Subscribe(b"foo")), Subscription::decode(b"\x01foo"));
+        assert_eq!(Some(Subscription::Unsubscribe(b"")), Subscription::decode(b"\x00"));
+        assert_eq!(None, Subscription::decode(b"\x02foo"));
+        assert_eq!(None, Subscription::decode(b""));
+
+        assert_eq!(b"\x01foo".to_vec(), Subscription::Subscribe(b"foo").encode());
+        assert_eq!(b"\x00foo".to_vec(), Subscription::Unsubscribe(b"foo").encode());
+        assert_eq!("unsubscribe: \"foo\"", Subscription::Unsubscribe(b"foo").to_string());
+    }
+
+    #[test]
+    fn stream_events() {
+        let mut peers = StreamPeers::default();
+        let connected = vec![vec![0, 0, 0, 0x80, 1], vec![]];
+        let data = vec![vec![0, 0, 0, 0x80, 1], b"data".to_vec()];
+
+        assert_eq!(Some(StreamEvent::Connected(&[0, 0, 0, 0x80, 1])), peers.event(&connected));
+        assert!(peers.contains(&[0, 0, 0, 0x80, 1]));
+        assert_eq!(Some(StreamEvent::Data(&[0, 0, 0, 0x80, 1], b"data")), peers.event(&data));
+        assert_eq!(Some(StreamEvent::Disconnected(&[0, 0, 0, 0x80, 1])), peers.event(&connected));
+        assert!(!peers.contains(&[0, 0, 0, 0x80, 1]));
+        assert_eq!(None, peers.event(&[vec![1]]));
+    }
+
+    #[test]
+    fn peer_ids() {
+        assert_eq!("0000008001", format_peer_id(&[0, 0, 0, 0x80, 1]));
+        assert_eq!(vec![0, 0, 0, 0x80, 1], parse_peer_id("0000008001").unwrap());
+        assert!(parse_peer_id("000").is_err());
+        assert!(parse_peer_id("zz").is_err());
+    }
+
+    #[test]
+    fn zap_on_two_sockets_of_one_context() {
+        let ctx = zmq::Context::new();
+        let server = |address: &'static str, zap| create_socket(&ctx, &SocketParameters {
+            address: address.into(),
+            socket_type: SocketType::PULL,
+            association_type: AssociationType::Bind,
+            zap,
+            ..Default::default()
+        });
+        let allowing = server("tcp://127.0.0.1:5590", ZapParameters { allow: vec!["127.0.0.1".into()], ..Default::default() }).unwrap();
+        let denying = server("tcp://127.0.0.1:5591", ZapParameters { deny: vec!["127.0.0.1".into()], ..Default::default() }).unwrap();
+
+        let mut clients = Vec::new();
+        for (server, address) in [(&allowing, "tcp://127.0.0.1:5590"), (&denying, "tcp://127.0.0.1:5591")] {
+            server.set_rcvtimeo(500).unwrap();
+            let client = ctx.socket(zmq::PUSH).unwrap();
+            client.set_linger(0).unwrap();
+            client.set_sndtimeo(500).unwrap();
+            client.connect(address).unwrap();
+            // Times out to the denying server, as the connection never completes
+            let _ = client.send("ZAP", 0);
+            clients.push(client);
+        }
+
+        assert_eq!(b"ZAP".to_vec(), allowing.recv_bytes(0).unwrap());
+        assert!(denying.recv_bytes(0).is_err());
+    }
+
+    #[test]
+    fn parsing_invalid_json_gives_error() {
+        let error = parse(r#"{ "address": "tcp://localhost:5559", "#).err().unwrap();
+        assert!(error.to_string().starts_with("Invalid socket configuration"));
+
+        let error = parse(r#"{ "address": "tcp://localhost:5559" }"#).err().unwrap();
+        assert!(error.to_string().contains("missing field `socket_type`"));
     }
 }
 
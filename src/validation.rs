@@ -1,3 +1,5 @@
+const TRANSPORTS: &[&str] = &["tcp", "ipc", "inproc", "pgm", "epgm", "ws", "wss", "tipc", "vmci"];
+
 ///
 /// ```rust
 ///  use rzmq::validation::validate_socket;
@@ -5,15 +7,206 @@
 ///  assert!(validate_socket("ipc://socket".to_string()).is_ok());
 ///  assert!(validate_socket("ipc://socket/in/path".to_string()).is_ok());
 ///  assert!(validate_socket("ipc:///tmp/socket/in/tmp".to_string()).is_ok());
+///  assert!(validate_socket("ipc://".to_string()).is_err());
 ///  assert!(validate_socket("tcp://localhost:5559".to_string()).is_ok());
 ///  assert!(validate_socket("tcp://127.0.0.1:666".to_string()).is_ok());
+///  assert!(validate_socket("tcp://*:5559".to_string()).is_ok());
+///  assert!(validate_socket("tcp://127.0.0.1:*".to_string()).is_ok());
+///  assert!(validate_socket("tcp://[::1]:5559".to_string()).is_ok());
+///  assert!(validate_socket("tcp://eth0;192.168.1.1:5559".to_string()).is_ok());
+///  assert!(validate_socket("tcp://".to_string()).is_err());
+///  assert!(validate_socket("tcp://localhost".to_string()).is_err());
+///  assert!(validate_socket("tcp://localhost:http".to_string()).is_err());
+///  assert!(validate_socket("tcp://localhost:70000".to_string()).is_err());
 ///  assert!(validate_socket("tcp:://not/ok".to_string()).is_err());
+///  assert!(validate_socket("inproc://workers".to_string()).is_ok());
+///  assert!(validate_socket("inproc://".to_string()).is_err());
+///  assert!(validate_socket("pgm://eth0;239.192.1.1:5555".to_string()).is_ok());
+///  assert!(validate_socket("epgm://192.168.1.2;239.192.1.1:5555".to_string()).is_ok());
+///  assert!(validate_socket("pgm://239.192.1.1:5555".to_string()).is_err());
+///  assert!(validate_socket("epgm://eth0;192.168.1.1:5555".to_string()).is_err());
+///  assert!(validate_socket("ws://localhost:8080/path".to_string()).is_ok());
+///  assert!(validate_socket("wss://*:443".to_string()).is_ok());
+///  assert!(validate_socket("tipc://{5560,0,0}".to_string()).is_ok());
+///  assert!(validate_socket("tipc://<*>".to_string()).is_ok());
+///  assert!(validate_socket("tipc://5560".to_string()).is_err());
+///  assert!(validate_socket("vmci://2:5555".to_string()).is_ok());
+///  assert!(validate_socket("vmci://*:*".to_string()).is_ok());
+///  assert!(validate_socket("udp://127.0.0.1:5555".to_string()).is_err());
 /// ```
 pub fn validate_socket(input: String) -> Result<(), String> {
-    if regex::Regex::new("ipc://.*|tcp://.*").unwrap().is_match(input.as_str())
-    {
+    let (transport, address) = match input.find("://") {
+        Some(index) => (&input[..index], &input[index + 3..]),
+        None => return Err(format!("Incorrect address {}, expected transport://address, e.g. tcp://127.0.0.1:5559", input)),
+    };
+
+    match transport {
+        "tcp" => validate_tcp(address),
+        "ipc" if address.is_empty() => Err("ipc address needs a path, e.g. ipc:///tmp/socket".to_string()),
+        "inproc" if address.is_empty() => Err("inproc address needs a name, e.g. inproc://workers".to_string()),
+        "ipc" | "inproc" => Ok(()),
+        "pgm" | "epgm" => validate_pgm(transport, address),
+        "ws" | "wss" => {
+            let host_port = address.split('/').next().unwrap();
+            validate_host_port(host_port).map_err(|e| format!("{} address needs host:port[/path]: {}", transport, e))
+        }
+        "tipc" => validate_tipc(address),
+        "vmci" => validate_vmci(address),
+        _ => Err(format!("Unknown transport {:?}, expected one of {}", transport, TRANSPORTS.join(", "))),
+    }
+}
+
+/// `host:port` optionally preceded by the source to connect from, `source;host:port`
+fn validate_tcp(address: &str) -> Result<(), String> {
+    let error = |e| format!("tcp address needs host:port or *:port, e.g. tcp://127.0.0.1:5559: {}", e);
+    let destination = match address.rfind(';') {
+        Some(index) => {
+            let source = &address[..index];
+            match split_port(source) {
+                Some((host, port)) if validate_port(port).is_ok() => validate_host(host),
+                _ => validate_host(source),
+            }.map_err(|e| error(format!("source {}", e)))?;
+            &address[index + 1..]
+        }
+        None => address,
+    };
+    validate_host_port(destination).map_err(error)
+}
+
+/// `interface;multicast:port`, where the interface is a name or an address
+fn validate_pgm(transport: &str, address: &str) -> Result<(), String> {
+    let error = |e: String| format!("{} address needs interface;multicast:port, e.g. {}://eth0;239.192.1.1:5555: {}",
+                                    transport, transport, e);
+    let index = address.find(';').ok_or_else(|| error("missing interface".to_string()))?;
+    let (interface, group) = (&address[..index], &address[index + 1..]);
+    validate_host(interface).map_err(|e| error(format!("interface {}", e)))?;
+
+    let (multicast, port) = split_port(group).ok_or_else(|| error("missing port".to_string()))?;
+    match multicast.parse::<std::net::IpAddr>() {
+        Ok(ip) if ip.is_multicast() => (),
+        Ok(_) => return Err(error(format!("{} is not a multicast address", multicast))),
+        Err(_) => return Err(error(format!("{:?} is not an IP address", multicast))),
+    }
+    if port == "*" {
+        return Err(error("the port cannot be a wildcard".to_string()));
+    }
+    validate_port(port).map_err(error)
+}
+
+/// `{type,lower,upper}` or `{type,instance}` for a service, `<*>` or `<zone.cluster.node:ref>` for a socket id
+fn validate_tipc(address: &str) -> Result<(), String> {
+    let numbers = |inner: &str, separators: &[char]| !inner.is_empty()
+        && inner.split(separators).all(|n| n.parse::<u32>().is_ok());
+    let valid = if address.starts_with('{') && address.ends_with('}') && address.len() > 1 {
+        let inner = &address[1..address.len() - 1];
+        numbers(inner, &[',']) && (2..=3).contains(&inner.split(',').count())
+    } else if address.starts_with('<') && address.ends_with('>') && address.len() > 1 {
+        let inner = &address[1..address.len() - 1];
+        inner == "*" || (numbers(inner, &['.', ':']) && inner.split(&['.', ':'][..]).count() == 4)
+    } else {
+        false
+    };
+    if valid {
         Ok(())
     } else {
-        Err("Incorrect address".to_string())
+        Err(format!("Incorrect tipc address {:?}, expected {{type,lower,upper}}, {{type,instance}} or <zone.cluster.node:ref>", address))
+    }
+}
+
+/// `cid:port` where both may be `*` when binding
+fn validate_vmci(address: &str) -> Result<(), String> {
+    let error = |e| format!("vmci address needs cid:port, e.g. vmci://2:5555: {}", e);
+    let (cid, port) = split_port(address).ok_or_else(|| error("missing port".to_string()))?;
+    if cid != "*" && cid.parse::<u32>().is_err() {
+        return Err(error(format!("{:?} is not a context id", cid)));
+    }
+    validate_port(port).map_err(error)
+}
+
+fn split_port(address: &str) -> Option<(&str, &str)> {
+    let index = address.rfind(':')?;
+    Some((&address[..index], &address[index + 1..]))
+}
+
+fn validate_host_port(address: &str) -> Result<(), String> {
+    let (host, port) = split_port(address).ok_or_else(|| format!("missing port in {:?}", address))?;
+    validate_host(host)?;
+    validate_port(port)
+}
+
+/// `*`, an IPv4 address, a bracketed IPv6 address, a host or an interface name
+fn validate_host(host: &str) -> Result<(), String> {
+    let valid = if host.starts_with('[') && host.ends_with(']') && host.len() > 1 {
+        host[1..host.len() - 1].parse::<std::net::Ipv6Addr>().is_ok()
+    } else {
+        host == "*" || regex::Regex::new(r"^[A-Za-z0-9_]([A-Za-z0-9_.-]*[A-Za-z0-9_])?$").unwrap().is_match(host)
+    };
+    match (host.is_empty(), valid) {
+        (true, _) => Err("missing host".to_string()),
+        (false, true) => Ok(()),
+        (false, false) => Err(format!("{:?} is not a host name or address", host)),
+    }
+}
+
+fn validate_port(port: &str) -> Result<(), String> {
+    if port == "*" || port.parse::<u16>().is_ok() {
+        Ok(())
+    } else {
+        Err(format!("{:?} is not a port number", port))
+    }
+}
+
+///
+/// ```rust
+///  use rzmq::validation::validate_number;
+///  assert!(validate_number("1000".to_string()).is_ok());
+///  assert!(validate_number("-1".to_string()).is_ok());
+///  assert!(validate_number("10ms".to_string()).is_err());
+/// ```
+pub fn validate_number(input: String) -> Result<(), String> {
+    input.parse::<i64>()
+        .map(|_| ())
+        .or_else(|_| input.parse::<u64>().map(|_| ()))
+        .map_err(|_| format!("Expected a number, got {}", input))
+}
+
+///
+/// ```rust
+///  use rzmq::validation::validate_duration;
+///  assert!(validate_duration("5s".to_string()).is_ok());
+///  assert!(validate_duration("250ms".to_string()).is_ok());
+///  assert!(validate_duration("2m".to_string()).is_ok());
+///  assert!(validate_duration("10".to_string()).is_ok());
+///  assert!(validate_duration("5 s".to_string()).is_err());
+///  assert!(validate_duration("soon".to_string()).is_err());
+/// ```
+pub fn validate_duration(input: String) -> Result<(), String> {
+    parse_duration(&input).map(|_| ())
+}
+
+///
+/// ```rust
+///  use rzmq::validation::parse_duration;
+///  use std::time::Duration;
+///  assert_eq!(Ok(Duration::from_millis(250)), parse_duration("250ms"));
+///  assert_eq!(Ok(Duration::from_secs(5)), parse_duration("5s"));
+///  assert_eq!(Ok(Duration::from_secs(5)), parse_duration("5"));
+///  assert_eq!(Ok(Duration::from_secs(120)), parse_duration("2m"));
+///  assert_eq!(Ok(Duration::from_secs(3600)), parse_duration("1h"));
+///  assert!(parse_duration("9999999999999999999h").is_err());
+///  assert!(parse_duration("99999999999999999999").is_err());
+/// ```
+pub fn parse_duration(input: &str) -> Result<std::time::Duration, String> {
+    let error = || format!("Incorrect duration {}, expected e.g. 500ms, 5s, 2m or 1h", input);
+    let split = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+    let (value, unit) = input.split_at(split);
+    let value = value.parse::<u64>().map_err(|_| error())?;
+
+    match unit {
+        "ms" => Ok(std::time::Duration::from_millis(value)),
+        "" | "s" => Ok(std::time::Duration::from_secs(value)),
+        "m" => Ok(std::time::Duration::from_secs(value.checked_mul(60).ok_or_else(error)?)),
+        "h" => Ok(std::time::Duration::from_secs(value.checked_mul(3600).ok_or_else(error)?)),
+        _ => Err(error()),
     }
 }
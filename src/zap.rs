@@ -0,0 +1,218 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use serde::{Deserialize, Serialize};
+use crate::security::{Key, certificate_key};
+
+const ZAP_ENDPOINT: &str = "inproc://zeromq.zap.01";
+
+/// Policies by the ZAP domain of the socket they apply to, shared by the handlers of all contexts
+static POLICIES: Mutex<BTreeMap<String, Arc<ZapPolicy>>> = Mutex::new(BTreeMap::new());
+
+/// Policies of the built-in ZAP handler. Any of them enables the handler.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ZapParameters<'a> {
+    /// File with `username=password` lines checked for PLAIN clients
+    #[serde(borrow, skip_serializing_if = "Option::is_none")]
+    pub users: Option<Cow<'a, str>>,
+    #[serde(borrow, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<Cow<'a, str>>,
+    #[serde(borrow, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<Cow<'a, str>>,
+    /// Directory of certificates with public keys of allowed CURVE clients
+    #[serde(borrow, skip_serializing_if = "Option::is_none")]
+    pub curve_certs: Option<Cow<'a, str>>,
+}
+
+impl ZapParameters<'_> {
+    pub fn is_enabled(&self) -> bool {
+        self.users.is_some() || !self.allow.is_empty() || !self.deny.is_empty() || self.curve_certs.is_some()
+    }
+}
+
+#[derive(Default)]
+pub struct ZapPolicy {
+    users: Option<HashMap<String, String>>,
+    allow: Vec<String>,
+    deny: Vec<String>,
+    curve_keys: Option<HashSet<Vec<u8>>>,
+}
+
+impl ZapPolicy {
+    pub fn load(parameters: &ZapParameters) -> Result<Self, Box<dyn Error>> {
+        let users = match parameters.users.as_deref() {
+            Some(path) => Some(parse_users(&fs::read_to_string(path)
+                .map_err(|e| format!("Cannot read users file {}: {}", path, e))?)),
+            None => None,
+        };
+
+        let curve_keys = match parameters.curve_certs.as_deref() {
+            Some(dir) => Some(load_curve_keys(dir)?),
+            None => None,
+        };
+
+        Ok(Self {
+            users,
+            allow: parameters.allow.iter().map(|a| a.to_string()).collect(),
+            deny: parameters.deny.iter().map(|a| a.to_string()).collect(),
+            curve_keys,
+        })
+    }
+
+    /// Returns the user id of an accepted peer or the reason it was denied
+    pub fn authenticate(&self, mechanism: &str, address: &str, credentials: &[Vec<u8>]) -> Result<String, String> {
+        if self.deny.iter().any(|a| a == address) {
+            return Err(format!("address {} is denied", address));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|a| a == address) {
+            return Err(format!("address {} is not allowed", address));
+        }
+
+        match (mechanism, credentials) {
+            ("PLAIN", [username, password]) => {
+                let username = String::from_utf8_lossy(username).to_string();
+                match &self.users {
+                    Some(users) if users.get(&username).map(String::as_bytes) != Some(password.as_slice()) =>
+                        Err(format!("invalid password for user {:?}", username)),
+                    _ => Ok(username),
+                }
+            }
+            ("CURVE", [key]) => {
+                let key_z85 = zmq::z85_encode(key).unwrap_or_default();
+                match &self.curve_keys {
+                    Some(keys) if !keys.contains(key) => Err(format!("unknown public key {}", key_z85)),
+                    _ => Ok(key_z85),
+                }
+            }
+            ("NULL", []) => Ok(String::new()),
+            (mechanism, _) => Err(format!("unsupported mechanism {}", mechanism)),
+        }
+    }
+}
+
+fn parse_users(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, '=');
+            Some((parts.next()?.trim().to_string(), parts.next()?.trim().to_string()))
+        })
+        .collect()
+}
+
+fn load_curve_keys(dir: &str) -> Result<HashSet<Vec<u8>>, Box<dyn Error>> {
+    let mut keys = HashSet::new();
+    for entry in fs::read_dir(dir).map_err(|e| format!("Cannot read certificates directory {}: {}", dir, e))? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        if let Some(key) = fs::read_to_string(&path).ok().and_then(|c| certificate_key(&c, Key::Public)) {
+            keys.insert(zmq::z85_decode(&key)?);
+        }
+    }
+    Ok(keys)
+}
+
+/// Enables the policy for a socket, returns the ZAP domain to set on it. The handler of the
+/// context is started with the first policy, before the server socket binds, and stays alive
+/// as long as the process does.
+pub fn register(ctx: &zmq::Context, policy: ZapPolicy) -> Result<String, Box<dyn Error>> {
+    let domain = {
+        let mut policies = POLICIES.lock().unwrap();
+        let domain = format!("rzmq.{}", policies.len());
+        policies.insert(domain.clone(), Arc::new(policy));
+        domain
+    };
+    start(ctx)?;
+    Ok(domain)
+}
+
+/// Starts the handler for sockets of the given context, unless one is already running
+fn start(ctx: &zmq::Context) -> Result<(), Box<dyn Error>> {
+    let handler = ctx.socket(zmq::REP)?;
+    handler.set_linger(0)?;
+    match handler.bind(ZAP_ENDPOINT) {
+        Err(zmq::Error::EADDRINUSE) => return Ok(()),
+        result => result?,
+    }
+
+    thread::spawn(move || {
+        while let Ok(request) = handler.recv_multipart(0) {
+            if request.len() < 6 {
+                continue;
+            }
+
+            let domain = String::from_utf8_lossy(&request[2]);
+            let address = String::from_utf8_lossy(&request[3]);
+            let mechanism = String::from_utf8_lossy(&request[5]);
+            let policy = POLICIES.lock().unwrap().get(domain.as_ref()).cloned();
+            let result = match policy {
+                Some(policy) => policy.authenticate(&mechanism, &address, &request[6..]),
+                None => Err(format!("unknown domain {:?}", domain)),
+            };
+            let (status, text, user_id) = match result {
+                Ok(user_id) => {
+                    eprintln!("auth: allowed {} {:?} from {}", mechanism, user_id, address);
+                    ("200", "OK".to_string(), user_id)
+                }
+                Err(reason) => {
+                    eprintln!("auth: denied {} from {}: {}", mechanism, address, reason);
+                    ("400", reason, String::new())
+                }
+            };
+
+            let reply: [&[u8]; 6] = [b"1.0", &request[1], status.as_bytes(), text.as_bytes(), user_id.as_bytes(), b""];
+            if handler.send_multipart(reply, 0).is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parsing_users_file() {
+        let users = parse_users("# comment\nadmin=secret\n\n user = pass=word \n");
+        assert_eq!(Some(&"secret".to_string()), users.get("admin"));
+        assert_eq!(Some(&"pass=word".to_string()), users.get("user"));
+        assert_eq!(2, users.len());
+    }
+
+    #[test]
+    fn authenticating_peers() {
+        let policy = ZapPolicy {
+            users: Some(parse_users("admin=secret")),
+            deny: vec!["10.0.0.1".to_string()],
+            ..ZapPolicy::default()
+        };
+
+        assert_eq!(Ok("admin".to_string()),
+                   policy.authenticate("PLAIN", "127.0.0.1", &[b"admin".to_vec(), b"secret".to_vec()]));
+        assert!(policy.authenticate("PLAIN", "127.0.0.1", &[b"admin".to_vec(), b"wrong".to_vec()]).is_err());
+        assert!(policy.authenticate("PLAIN", "127.0.0.1", &[b"nobody".to_vec(), b"".to_vec()]).is_err());
+        assert_eq!(Err("address 10.0.0.1 is denied".to_string()), policy.authenticate("NULL", "10.0.0.1", &[]));
+        assert!(policy.authenticate("NULL", "127.0.0.1", &[]).is_ok());
+
+        let policy = ZapPolicy {
+            allow: vec!["127.0.0.1".to_string()],
+            curve_keys: Some(vec![vec![1; 32]].into_iter().collect()),
+            ..ZapPolicy::default()
+        };
+
+        assert!(policy.authenticate("CURVE", "127.0.0.1", &[vec![1; 32]]).is_ok());
+        assert!(policy.authenticate("CURVE", "127.0.0.1", &[vec![2; 32]]).is_err());
+        assert_eq!(Err("address 10.0.0.2 is not allowed".to_string()), policy.authenticate("NULL", "10.0.0.2", &[]));
+    }
+}
@@ -1,12 +1,13 @@
 use std::process::{Command, Stdio, Child, ChildStdout};
 use assert_cmd::prelude::*;
 use std::io::{Write};
+use std::net::TcpStream;
 use std::thread::sleep;
 use std::time::Duration;
 use nonblock::NonBlockingReader;
 use std::ops::{Deref, DerefMut};
 
-use rzmq::{chat, socket};
+use rzmq::{chat, encoding, security, socket};
 
 fn test_push_pull_send_listen() {
     let test_message = "TEST MESSAGE 12345";
@@ -20,8 +21,8 @@ fn test_push_pull_send_listen() {
 fn test_push_pull_with_json_config() {
     let test_message = "TEST MESSAGE 12345";
 
-    let mut listener = run_instance("listen --config test_config.json").unwrap();
-    let _send = run_instance(format!("send --message {} --config test_config.json --type PUSH --connect", test_message).as_str()).unwrap();
+    let mut listener = run_instance("listen --config tests/test_config.json").unwrap();
+    let _send = run_instance(format!("send --message {} --config tests/test_config.json --type PUSH --connect", test_message).as_str()).unwrap();
 
     assert!(listener.wait_for_message(test_message).is_ok());
 }
@@ -44,17 +45,19 @@ fn test_pub_sub() {
 
 fn test_pair_chat() {
     let instance1 = chat::Chat::new(&socket::SocketParameters{
-        address: "tcp://127.0.0.1:5559",
+        address: "tcp://127.0.0.1:5559".into(),
         association_type: socket::AssociationType::Bind,
         socket_type: socket::SocketType::PAIR,
         socket_id: None,
-        topic: None}).unwrap();
+        topic: None,
+        ..Default::default()}).unwrap();
     let instance2 = chat::Chat::new(&socket::SocketParameters{
-        address: "tcp://127.0.0.1:5559",
+        address: "tcp://127.0.0.1:5559".into(),
         association_type: socket::AssociationType::Connect,
         socket_type: socket::SocketType::PAIR,
         socket_id: None,
-        topic: None}).unwrap();
+        topic: None,
+        ..Default::default()}).unwrap();
 
 
     instance1.send("Hi!").unwrap();
@@ -71,23 +74,26 @@ fn test_pair_chat() {
 
 fn test_router_dealer_chat() {
     let router = chat::Chat::new(&socket::SocketParameters{
-        address: "tcp://127.0.0.1:5559",
+        address: "tcp://127.0.0.1:5559".into(),
         association_type: socket::AssociationType::Bind,
         socket_type: socket::SocketType::ROUTER,
         socket_id: None,
-        topic: None}).unwrap();
+        topic: None,
+        ..Default::default()}).unwrap();
     let dealer = chat::Chat::new(&socket::SocketParameters{
-        address: "tcp://127.0.0.1:5559",
+        address: "tcp://127.0.0.1:5559".into(),
         association_type: socket::AssociationType::Connect,
         socket_type: socket::SocketType::DEALER,
-        socket_id: Some("ID1"),
-        topic: None}).unwrap();
+        socket_id: Some("ID1".into()),
+        topic: None,
+        ..Default::default()}).unwrap();
     let dealer2 = chat::Chat::new(&socket::SocketParameters{
-        address: "tcp://127.0.0.1:5559",
+        address: "tcp://127.0.0.1:5559".into(),
         association_type: socket::AssociationType::Connect,
         socket_type: socket::SocketType::DEALER,
-        socket_id: Some("ID2"),
-        topic: None}).unwrap();
+        socket_id: Some("ID2".into()),
+        topic: None,
+        ..Default::default()}).unwrap();
 
 
     dealer.send("MSG1").unwrap();
@@ -106,6 +112,348 @@ fn test_router_dealer_chat() {
 
 }
 
+fn test_xpub_subscriptions() {
+    let mut listener = run_instance("listen --address tcp://127.0.0.1:5559 --type XPUB --bind").unwrap();
+    let subscriber = chat::Chat::new(&socket::SocketParameters{
+        address: "tcp://127.0.0.1:5559".into(),
+        association_type: socket::AssociationType::Connect,
+        socket_type: socket::SocketType::XSUB,
+        socket_id: None,
+        topic: Some("TOPIC1".into()),
+        ..Default::default()}).unwrap();
+
+    assert!(listener.wait_for_message("subscribe: \"TOPIC1\"").is_ok());
+
+    subscriber.unsubscribe("TOPIC1").unwrap();
+    assert!(listener.wait_for_message("unsubscribe: \"TOPIC1\"").is_ok());
+}
+
+fn test_stream_raw_tcp() {
+    let mut listener = run_instance("listen --address tcp://127.0.0.1:5559 --type STREAM --bind").unwrap();
+    sleep(Duration::from_millis(200));
+
+    let mut peer = TcpStream::connect("127.0.0.1:5559").unwrap();
+    assert!(listener.wait_for_message("connected: ").is_ok());
+
+    peer.write_all(b"raw bytes").unwrap();
+    assert!(listener.wait_for_message(": \"raw bytes\"").is_ok());
+
+    drop(peer);
+    assert!(listener.wait_for_message("disconnected: ").is_ok());
+}
+
+fn test_curve_pair_chat() {
+    let server_keys = zmq::CurveKeyPair::new().unwrap();
+    let server_public = zmq::z85_encode(&server_keys.public_key).unwrap();
+    let server_secret = zmq::z85_encode(&server_keys.secret_key).unwrap();
+
+    let server = chat::Chat::new(&socket::SocketParameters{
+        address: "tcp://127.0.0.1:5559".into(),
+        association_type: socket::AssociationType::Bind,
+        socket_type: socket::SocketType::PAIR,
+        curve: security::CurveParameters { server: true, secret_key: Some(server_secret.as_str().into()), ..Default::default() },
+        ..Default::default()}).unwrap();
+    let client = chat::Chat::new(&socket::SocketParameters{
+        address: "tcp://127.0.0.1:5559".into(),
+        association_type: socket::AssociationType::Connect,
+        socket_type: socket::SocketType::PAIR,
+        curve: security::CurveParameters { server_key: Some(server_public.as_str().into()), ..Default::default() },
+        ..Default::default()}).unwrap();
+
+    client.send("Secret").unwrap();
+    sleep(Duration::from_millis(500));
+
+    assert_eq!("Secret", server.receive().unwrap()[0].as_str());
+}
+
+fn test_plain_authentication() {
+    let listener = Command::cargo_bin("rzmq").unwrap()
+        .args("listen --address tcp://127.0.0.1:5559 --type PULL --bind --plain-server --zap-users tests/test_users.txt \
+            --count 1 --timeout 5s".split_whitespace())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let _rejected = run_instance("send --message REJECTED --address tcp://127.0.0.1:5559 --type PUSH --plain-username admin --plain-password wrong").unwrap();
+    sleep(Duration::from_millis(300));
+    let _accepted = run_instance("send --message ACCEPTED --address tcp://127.0.0.1:5559 --type PUSH --plain-username admin --plain-password secret").unwrap();
+
+    let output = listener.wait_with_output().unwrap();
+    let received = String::from_utf8_lossy(&output.stdout);
+    assert!(received.contains("ACCEPTED"));
+    assert!(!received.contains("auth:"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("auth: denied PLAIN"));
+}
+
+fn test_multipart_send() {
+    let listener = chat::Chat::new(&socket::SocketParameters{
+        address: "tcp://127.0.0.1:5559".into(),
+        association_type: socket::AssociationType::Bind,
+        socket_type: socket::SocketType::PULL,
+        ..Default::default()}).unwrap();
+    let _send = run_instance("send --frame HEADER --frame= --frame BODY --address tcp://127.0.0.1:5559 --type PUSH").unwrap();
+
+    sleep(Duration::from_millis(500));
+
+    assert_eq!(vec!["HEADER", "", "BODY"], listener.receive().unwrap());
+}
+
+fn test_binary_payloads() {
+    let mut listener = run_instance("listen --address tcp://127.0.0.1:5559 --type PULL --bind --encoding auto").unwrap();
+    let _send = run_instance("send --message 00ff10 --encoding hex --address tcp://127.0.0.1:5559 --type PUSH").unwrap();
+
+    assert!(listener.wait_for_message("received: 0x00ff10").is_ok());
+}
+
+fn test_send_line_delimited_stdin() {
+    let mut listener = run_instance("listen --address tcp://127.0.0.1:5559 --type PULL --bind").unwrap();
+    let mut send = run_instance("send --stdin --line-delimited --address tcp://127.0.0.1:5559 --type PUSH").unwrap();
+
+    send.write("LINE1\nLINE2\n");
+    drop(send.stdin.take());
+
+    assert!(listener.wait_for_message("received: \"LINE1\"\nreceived: \"LINE2\"").is_ok());
+}
+
+fn test_listen_jsonl_output() {
+    let mut listener = run_instance("listen --address tcp://127.0.0.1:5559 --type PULL --bind --output jsonl").unwrap();
+    let _send = run_instance("send --frame HEADER --frame BODY --address tcp://127.0.0.1:5559 --type PUSH").unwrap();
+
+    assert!(listener.wait_for_message(r#""frames":[{"data":"HEADER","encoding":"text","size":6},{"data":"BODY","encoding":"text","size":4}],"sequence":1"#).is_ok());
+}
+
+fn test_listen_count_and_timeout() {
+    let mut listener = run_instance("listen --address tcp://127.0.0.1:5559 --type PULL --bind --count 1 --timeout 5s").unwrap();
+    let _send = run_instance("send --message EXPECTED --address tcp://127.0.0.1:5559 --type PUSH").unwrap();
+
+    assert!(listener.wait_for_message("EXPECTED").is_ok());
+    assert_eq!(Some(0), listener.wait().unwrap().code());
+
+    let mut listener = run_instance("listen --address tcp://127.0.0.1:5559 --type PULL --bind --count 1 --timeout 200ms").unwrap();
+    assert_eq!(Some(2), listener.wait().unwrap().code());
+}
+
+fn test_send_readiness() {
+    let mut sender = run_instance("send --message LOST --address tcp://127.0.0.1:5559 --type PUSH --connect --ready-timeout 200ms").unwrap();
+    assert_eq!(Some(2), sender.wait().unwrap().code());
+
+    let _send = run_instance("send --message SUBSCRIBED --address tcp://127.0.0.1:5559 --type PUB --bind --ready subscription").unwrap();
+    let mut listener = run_instance("listen --address tcp://127.0.0.1:5559 --type SUB --connect").unwrap();
+
+    assert!(listener.wait_for_message("SUBSCRIBED").is_ok());
+}
+
+fn test_request_reply() {
+    let server = chat::Chat::new(&socket::SocketParameters{
+        address: "tcp://127.0.0.1:5559".into(),
+        association_type: socket::AssociationType::Bind,
+        socket_type: socket::SocketType::REP,
+        ..Default::default()}).unwrap();
+
+    let mut client = run_instance("send --message PING --address tcp://127.0.0.1:5559 --type REQ --timeout 2s").unwrap();
+
+    sleep(Duration::from_millis(500));
+    assert_eq!("PING", server.receive().unwrap()[0].as_str());
+    server.send("PONG").unwrap();
+
+    assert!(client.wait_for_message("received: \"PONG\"").is_ok());
+    assert_eq!(Some(0), client.wait().unwrap().code());
+
+    let mut client = run_instance("send --message PING --address tcp://127.0.0.1:5559 --type REQ --timeout 200ms").unwrap();
+    assert_eq!(Some(2), client.wait().unwrap().code());
+
+    // Gives up after the default timeout as well
+    let mut client = run_instance("send --message PING --address tcp://127.0.0.1:5559 --type REQ").unwrap();
+    assert_eq!(Some(2), client.wait().unwrap().code());
+}
+
+fn test_lazy_pirate_failover() {
+    let server = chat::Chat::new(&socket::SocketParameters{
+        address: "tcp://127.0.0.1:5559".into(),
+        association_type: socket::AssociationType::Bind,
+        socket_type: socket::SocketType::REP,
+        ..Default::default()}).unwrap();
+
+    let mut client = run_instance("request --message PING --address tcp://127.0.0.1:5560 --address tcp://127.0.0.1:5559 --timeout 300ms --retries 2").unwrap();
+
+    let request = (0..20).find_map(|_| server.receive().ok()).unwrap();
+    assert_eq!("PING", request[0].as_str());
+    server.send("PONG").unwrap();
+
+    assert!(client.wait_for_message("received: \"PONG\"").is_ok());
+    assert_eq!(Some(0), client.wait().unwrap().code());
+
+    let connected = Command::cargo_bin("rzmq").unwrap()
+        .args("request --message PING --connect tcp://127.0.0.1:5560".split_whitespace())
+        .output()
+        .unwrap();
+    assert!(!connected.status.success());
+    assert!(String::from_utf8_lossy(&connected.stderr).contains("rotate through --address"));
+}
+
+fn test_serve() {
+    let server = run_instance("serve --address tcp://127.0.0.1:5559 --type ROUTER --reply-template Hello,{body}!").unwrap();
+    let mut client = run_instance("send --message World --address tcp://127.0.0.1:5559 --type REQ --timeout 2s").unwrap();
+
+    assert!(client.wait_for_message("received: \"Hello,World!\"").is_ok());
+    drop(server);
+
+    let mut server = run_instance("serve --address tcp://127.0.0.1:5559 --exec rev").unwrap();
+    let mut client = run_instance("request --message abc --address tcp://127.0.0.1:5559").unwrap();
+
+    assert!(server.wait_for_message("received: \"abc\"").is_ok());
+    assert!(client.wait_for_message("received: \"cba").is_ok());
+}
+
+fn test_proxy() {
+    let capture_file = std::env::temp_dir().join(format!("rzmq-capture-{}.jsonl", std::process::id()));
+    let _proxy = run_instance(format!("proxy --frontend PULL,bind,tcp://127.0.0.1:5559 --backend PUSH,bind,tcp://127.0.0.1:5560 \
+                                       --capture tcp://127.0.0.1:5561 --capture-file {}", capture_file.display()).as_str()).unwrap();
+    let mut listener = run_instance("listen --address tcp://127.0.0.1:5560 --type PULL --connect").unwrap();
+    let mut tap = run_instance("listen --address tcp://127.0.0.1:5561 --type SUB --connect").unwrap();
+    sleep(Duration::from_millis(200));
+    let _send = run_instance("send --message PROXIED --address tcp://127.0.0.1:5559 --type PUSH --connect").unwrap();
+
+    assert!(listener.wait_for_message("PROXIED").is_ok());
+    assert!(tap.wait_for_message("PROXIED").is_ok());
+
+    let captured = std::fs::read_to_string(&capture_file).unwrap();
+    std::fs::remove_file(&capture_file).unwrap();
+    assert!(captured.contains(r#""direction":"frontend->backend""#));
+    assert!(captured.contains(r#""data":"PROXIED""#));
+}
+
+fn test_proxy_queue() {
+    let _proxy = run_instance("proxy --frontend ROUTER,bind,tcp://127.0.0.1:5559 --backend DEALER,bind,tcp://127.0.0.1:5560").unwrap();
+    let _server = run_instance("serve --address tcp://127.0.0.1:5560 --type REP --connect --reply-template QUEUED:{body}").unwrap();
+    let mut client = run_instance("request --message PING --address tcp://127.0.0.1:5559").unwrap();
+
+    assert!(client.wait_for_message("received: \"QUEUED:PING\"").is_ok());
+    assert_eq!(Some(0), client.wait().unwrap().code());
+}
+
+fn test_proxy_forwarder() {
+    let capture_file = std::env::temp_dir().join(format!("rzmq-forwarder-{}.jsonl", std::process::id()));
+    let _proxy = run_instance(format!("proxy --frontend XSUB,bind,tcp://127.0.0.1:5559 --backend XPUB,bind,tcp://127.0.0.1:5560 \
+                                       --capture-file {}", capture_file.display()).as_str()).unwrap();
+    let mut listener = run_instance("listen --address tcp://127.0.0.1:5560 --type SUB --topic A").unwrap();
+
+    // The publisher only learns about the subscription when the proxy forwards it upstream
+    let _send = run_instance("send --message A-FORWARDED --address tcp://127.0.0.1:5559 --type PUB --connect --ready subscription").unwrap();
+    assert!(listener.wait_for_message("A-FORWARDED").is_ok());
+
+    let _send = run_instance("send --message B-FILTERED --address tcp://127.0.0.1:5559 --type PUB --connect --ready subscription").unwrap();
+    assert!(listener.wait_for_message("B-FILTERED").is_err());
+
+    // Filtered by the publisher already, as the proxy subscribed upstream to nothing but A
+    let captured = std::fs::read_to_string(&capture_file).unwrap();
+    std::fs::remove_file(&capture_file).unwrap();
+    assert!(captured.contains("A-FORWARDED"));
+    assert!(!captured.contains("B-FILTERED"));
+}
+
+fn test_proxy_control() {
+    let mut proxy = run_instance("proxy --frontend PULL,bind,tcp://127.0.0.1:5559 --backend PUSH,bind,tcp://127.0.0.1:5560 \
+                                  --control tcp://127.0.0.1:5562").unwrap();
+    let mut listener = run_instance("listen --address tcp://127.0.0.1:5560 --type PULL --connect").unwrap();
+    let _send = run_instance("send --message COUNTED --address tcp://127.0.0.1:5559 --type PUSH --connect").unwrap();
+    assert!(listener.wait_for_message("COUNTED").is_ok());
+
+    let mut statistics = run_instance("proxy-ctl --address tcp://127.0.0.1:5562 STATISTICS").unwrap();
+    assert!(statistics.wait_for_message("frontend: received 1 messages (7 bytes), sent 0 messages (0 bytes)\n\
+                                         backend: received 0 messages (0 bytes), sent 1 messages (7 bytes)").is_ok());
+
+    assert_eq!(Some(0), run_instance("proxy-ctl --address tcp://127.0.0.1:5562 terminate").unwrap().wait().unwrap().code());
+    assert_eq!(Some(0), proxy.wait().unwrap().code());
+}
+
+fn test_monitor_events() {
+    let mut listener = run_instance("listen --address tcp://127.0.0.1:5559 --type PULL --bind").unwrap();
+    let sender = Command::cargo_bin("rzmq").unwrap()
+        .args("send --message MONITORED --address tcp://127.0.0.1:5559 --type PUSH --monitor".split_whitespace())
+        .output()
+        .unwrap();
+
+    assert!(listener.wait_for_message("MONITORED").is_ok());
+    let events = String::from_utf8_lossy(&sender.stderr);
+    assert!(events.contains("CONNECTED tcp://127.0.0.1:5559"));
+    assert!(events.contains("HANDSHAKE_SUCCEEDED tcp://127.0.0.1:5559"));
+    assert!(events.contains("MONITOR_STOPPED"));
+    assert!(sender.status.success());
+}
+
+fn test_multiple_endpoints() {
+    let mut listener = run_instance("listen --type SUB --connect tcp://127.0.0.1:5559 --connect tcp://127.0.0.1:5560").unwrap();
+    let _send1 = run_instance("send --message FROM_FIRST --address tcp://127.0.0.1:5559 --type PUB").unwrap();
+    assert!(listener.wait_for_message("FROM_FIRST").is_ok());
+    // Only sending once the first is read, as waiting for it consumes everything printed so far
+    let _send2 = run_instance("send --message FROM_SECOND --address tcp://127.0.0.1:5560 --type PUB").unwrap();
+    assert!(listener.wait_for_message("FROM_SECOND").is_ok());
+
+    let both = Command::cargo_bin("rzmq").unwrap()
+        .args("listen --type PULL --bind --connect --address tcp://127.0.0.1:5559".split_whitespace())
+        .output()
+        .unwrap();
+    assert!(!both.status.success());
+    assert!(String::from_utf8_lossy(&both.stderr).contains("not both"));
+}
+
+fn test_wildcard_bind_endpoint_file() {
+    let endpoint_file = std::env::temp_dir().join(format!("rzmq-endpoint-{}", std::process::id()));
+    let mut listener = run_instance(format!("listen --address tcp://127.0.0.1:* --type PULL --bind --endpoint-file {}",
+                                            endpoint_file.display()).as_str()).unwrap();
+
+    let mut endpoint = String::new();
+    for _ in 1..50 {
+        if let Ok(contents) = std::fs::read_to_string(&endpoint_file) {
+            endpoint = contents.trim().to_string();
+            break;
+        }
+        sleep(Duration::from_millis(100));
+    }
+    std::fs::remove_file(&endpoint_file).unwrap();
+    assert!(endpoint.starts_with("tcp://127.0.0.1:"));
+    assert!(!endpoint.ends_with(":*"));
+
+    let _send = run_instance(format!("send --message WILDCARD --address {} --type PUSH", endpoint).as_str()).unwrap();
+    assert!(listener.wait_for_message("WILDCARD").is_ok());
+}
+
+fn test_chat_endpoints_and_options() {
+    let mut server = chat::Chat::new(&socket::SocketParameters {
+        address: "tcp://127.0.0.1:5559".into(),
+        association_type: socket::AssociationType::Bind,
+        socket_type: socket::SocketType::PULL,
+        ..Default::default()}).unwrap();
+    let bound = server.add_endpoint(socket::AssociationType::Bind, "tcp://127.0.0.1:*").unwrap();
+    assert!(!bound.ends_with(":*"));
+
+    let mut client = chat::Chat::new(&socket::SocketParameters {
+        address: "tcp://127.0.0.1:5559".into(),
+        association_type: socket::AssociationType::Connect,
+        socket_type: socket::SocketType::PUSH,
+        socket_id: Some("CLIENT".into()),
+        ..Default::default()}).unwrap();
+    client.add_endpoint(socket::AssociationType::Connect, &bound).unwrap();
+    assert_eq!(format!("type: PUSH\nendpoints: connect tcp://127.0.0.1:5559, connect {}\nidentity: \"CLIENT\"", bound),
+               client.info().unwrap());
+
+    client.set_option("sndhwm", "10").unwrap();
+    assert!(client.set_option("rcvhwm", "10").is_err());
+    client.remove_endpoint(socket::AssociationType::Connect, "tcp://127.0.0.1:5559").unwrap();
+    client.send_multipart(&["HEADER".to_string(), "BODY".to_string()]).unwrap();
+    assert_eq!(["HEADER", "BODY"], server.receive().unwrap()[..]);
+
+    assert_eq!(encoding::Encoding::Hex, server.toggle_hex());
+    client.send("HEX").unwrap();
+    assert_eq!("484558", server.receive().unwrap()[0].as_str());
+    assert_eq!(encoding::Encoding::Text, server.toggle_hex());
+
+    server.remove_endpoint(socket::AssociationType::Bind, &bound).unwrap();
+    assert!(server.remove_endpoint(socket::AssociationType::Bind, &bound).is_err());
+}
+
 #[test]
 fn integration_tests() {
     test_push_pull_send_listen();
@@ -113,6 +461,27 @@ fn integration_tests() {
     test_pub_sub();
     test_pair_chat();
     test_router_dealer_chat();
+    test_xpub_subscriptions();
+    test_stream_raw_tcp();
+    test_curve_pair_chat();
+    test_plain_authentication();
+    test_multipart_send();
+    test_binary_payloads();
+    test_send_line_delimited_stdin();
+    test_listen_jsonl_output();
+    test_listen_count_and_timeout();
+    test_send_readiness();
+    test_request_reply();
+    test_lazy_pirate_failover();
+    test_serve();
+    test_proxy();
+    test_proxy_queue();
+    test_proxy_forwarder();
+    test_proxy_control();
+    test_monitor_events();
+    test_multiple_endpoints();
+    test_wildcard_bind_endpoint_file();
+    test_chat_endpoints_and_options();
 }
 
 fn run_instance(args: &str) -> Result<Wrapper, String> {
@@ -129,7 +498,7 @@ fn run_instance(args: &str) -> Result<Wrapper, String> {
                 .map_err(|e| e.to_string())
         }).map(|mut c| {
         let reader = NonBlockingReader::from_fd(c.stdout.take().unwrap()).unwrap();
-        Wrapper{ 0: c, 1: reader}
+        Wrapper(c, reader)
     })
 }
 
@@ -138,6 +507,7 @@ struct Wrapper(Child, NonBlockingReader<ChildStdout>);
 impl Drop for Wrapper {
     fn drop(&mut self) {
         self.kill().unwrap();
+        self.wait().unwrap();
     }
 }
 
@@ -171,8 +541,8 @@ impl Wrapper {
         Err("Message not received")
     }
 
-    fn _write(&mut self, input: &str) {
-        self.stdin.as_mut().unwrap().write(input.as_bytes()).unwrap();
+    fn write(&mut self, input: &str) {
+        self.stdin.as_mut().unwrap().write_all(input.as_bytes()).unwrap();
     }
 }
 